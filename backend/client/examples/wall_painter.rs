@@ -0,0 +1,29 @@
+//! 壁にぶつかるまで歩き、ポイントがあれば塗るだけのボット
+//!
+//! ```text
+//! cargo run -p kani-life-client --example wall_painter -- http://localhost:8000
+//! ```
+
+use kani_life_client::{Crab, Error, Side, SocketTransport, WhatYouCanSee};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let origin = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "http://localhost:8000".to_string());
+    let transport = SocketTransport::connect(&origin).await?;
+    let mut crab = Crab::spawn(transport, "wall-painter", 200.0).await?;
+    loop {
+        let walked = crab.walk(Side::Right).await?;
+        if !walked.success {
+            crab.turn(Side::Left).await?;
+            continue;
+        }
+        if walked.total_point > 0 {
+            crab.paint().await?;
+        }
+        if crab.scan().await? == WhatYouCanSee::Food {
+            println!("food ahead!");
+        }
+    }
+}
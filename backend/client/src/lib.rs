@@ -0,0 +1,353 @@
+//! kani-life のカニを操作するボットを書くためのクライアント
+//!
+//! ```no_run
+//! use kani_life_client::{Crab, HttpTransport, Side};
+//!
+//! # async fn run() -> Result<(), kani_life_client::Error> {
+//! let transport = HttpTransport::new("http://localhost:8000");
+//! let mut crab = Crab::spawn(transport, "kani", 350.0).await?;
+//! loop {
+//!     if crab.walk(Side::Right).await?.success {
+//!         crab.paint().await?;
+//!     } else {
+//!         crab.turn(Side::Left).await?;
+//!     }
+//! }
+//! # }
+//! ```
+
+mod transport;
+
+pub use kani_life_protocol::player_command::{
+    PaintResult, PinchResult, SandboxMode, ScanResult, SpawnParam, StageProgress,
+    StageStatusResult, WaitForResult, WaitReason, WalkResult, WhatYouCanSee,
+};
+pub use kani_life_protocol::{CommandResult, Direction, PlayerCommand, Position, Side, Token};
+pub use transport::{HttpTransport, SocketTransport, Transport};
+
+use kani_life_protocol::player_command::{
+    GraduateParam, PaintParam, PinchParam, ResetSandboxParam, ScanParam, StageStatusParam,
+    StartStageParam, TurnParam, WaitForParam, WalkParam,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    Socket(Box<tokio_tungstenite::tungstenite::Error>),
+    Json(serde_json::Error),
+    /// ソケットがサーバーから閉じられた
+    Closed,
+    /// サーバーがコマンドを受け付けなかった (読めない JSON を送ったなど)
+    Server(String),
+    /// token に一致するカニが見つからなかった (同じ名前で Spawn し直されたなど)
+    CrabNotFound,
+    /// エネルギーが 0 で行動できなかった
+    OutOfEnergy,
+    /// 指定した ID のステージが見つからなかった
+    StageNotFound,
+    /// 遊んでいるステージでは使えないコマンドや、動けない間に行動するコマンドを送った
+    CommandNotAllowed,
+    /// サンドボックスにいないカニでサンドボックスのコマンドを送った
+    SandboxNotFound,
+    /// コマンドに対応しない結果が返ってきた
+    UnexpectedResult(CommandResult),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "http error: {}", e),
+            Error::Socket(e) => write!(f, "socket error: {}", e),
+            Error::Json(e) => write!(f, "json error: {}", e),
+            Error::Closed => write!(f, "socket closed by server"),
+            Error::Server(message) => write!(f, "server error: {}", message),
+            Error::CrabNotFound => write!(f, "crab not found"),
+            Error::OutOfEnergy => write!(f, "out of energy"),
+            Error::StageNotFound => write!(f, "stage not found"),
+            Error::CommandNotAllowed => write!(f, "command not allowed now"),
+            Error::SandboxNotFound => write!(f, "crab is not in a sandbox"),
+            Error::UnexpectedResult(result) => write!(f, "unexpected result: {:?}", result),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        Error::Socket(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+/// 出現させたカニを操作するハンドル
+///
+/// 各メソッドはサーバーがコマンドごとに課す待ち時間が過ぎてから返る
+#[derive(Debug)]
+pub struct Crab<T: Transport> {
+    transport: T,
+    token: Token,
+}
+
+impl<T: Transport> Crab<T> {
+    /// カニをフィールドに出現させる
+    /// 同じ名前のカニがすでにいる場合は、そのカニは除去される
+    pub async fn spawn(transport: T, name: &str, hue: f32) -> Result<Self, Error> {
+        Self::spawn_with(transport, SpawnParam::new(name, hue)).await
+    }
+
+    /// チームに入ってカニを出現させる。チームのカニはポイントとペイントの色を共有する
+    pub async fn spawn_in_team(
+        transport: T,
+        name: &str,
+        hue: f32,
+        team: &str,
+    ) -> Result<Self, Error> {
+        let param = SpawnParam {
+            team: Some(team.to_string()),
+            ..SpawnParam::new(name, hue)
+        };
+        Self::spawn_with(transport, param).await
+    }
+
+    /// 自分だけの練習用サンドボックスにカニを出現させる。
+    /// 準備ができたら `graduate` でみんなのフィールドに出られる
+    pub async fn spawn_in_sandbox(
+        transport: T,
+        name: &str,
+        hue: f32,
+        mode: SandboxMode,
+    ) -> Result<Self, Error> {
+        let param = SpawnParam {
+            sandbox: Some(mode),
+            ..SpawnParam::new(name, hue)
+        };
+        Self::spawn_with(transport, param).await
+    }
+
+    async fn spawn_with(mut transport: T, param: SpawnParam) -> Result<Self, Error> {
+        match transport.send(&PlayerCommand::Spawn(param)).await? {
+            CommandResult::Spawn(result) => Ok(Crab {
+                transport,
+                token: result.token,
+            }),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// 以前に出現させたカニのトークンから操作を再開する
+    pub fn with_token(transport: T, token: Token) -> Self {
+        Crab { transport, token }
+    }
+
+    pub fn token(&self) -> Token {
+        self.token
+    }
+
+    /// 正面になにが見えるか調べる
+    pub async fn scan(&mut self) -> Result<WhatYouCanSee, Error> {
+        let command = PlayerCommand::Scan(ScanParam { token: self.token });
+        match self.send(&command).await? {
+            CommandResult::Scan(result) => Ok(result.what_you_can_see),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// その場で左右に回転する
+    pub async fn turn(&mut self, side: Side) -> Result<(), Error> {
+        let command = PlayerCommand::Turn(TurnParam {
+            token: self.token,
+            side,
+        });
+        match self.send(&command).await? {
+            CommandResult::Turn => Ok(()),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// 左右に 1 マス移動する
+    pub async fn walk(&mut self, side: Side) -> Result<WalkResult, Error> {
+        let command = PlayerCommand::Walk(WalkParam {
+            token: self.token,
+            side,
+        });
+        match self.send(&command).await? {
+            CommandResult::Walk(result) => Ok(result),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// 今いるマスを塗る
+    pub async fn paint(&mut self) -> Result<PaintResult, Error> {
+        let command = PlayerCommand::Paint(PaintParam { token: self.token });
+        match self.send(&command).await? {
+            CommandResult::Paint(result) => Ok(result),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// 正面のカニをはさむ
+    pub async fn pinch(&mut self) -> Result<PinchResult, Error> {
+        let command = PlayerCommand::Pinch(PinchParam { token: self.token });
+        match self.send(&command).await? {
+            CommandResult::Pinch(result) => Ok(result),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// フィールドを離れてパズルのステージを (はじめから) 始める
+    pub async fn start_stage(&mut self, stage: &str) -> Result<StageStatusResult, Error> {
+        let command = PlayerCommand::StartStage(StartStageParam {
+            token: self.token,
+            stage: stage.to_string(),
+        });
+        match self.send(&command).await? {
+            CommandResult::StageStatus(result) => Ok(result),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// 遊んでいるステージの進み具合を調べる
+    pub async fn stage_status(&mut self) -> Result<StageStatusResult, Error> {
+        let command = PlayerCommand::StageStatus(StageStatusParam { token: self.token });
+        match self.send(&command).await? {
+            CommandResult::StageStatus(result) => Ok(result),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// サンドボックスをはじめからやり直す
+    pub async fn reset_sandbox(&mut self) -> Result<(), Error> {
+        let command = PlayerCommand::ResetSandbox(ResetSandboxParam { token: self.token });
+        match self.send(&command).await? {
+            CommandResult::Spawn(_) => Ok(()),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// サンドボックスを卒業して、同じトークンのままみんなのフィールドに出る
+    pub async fn graduate(&mut self) -> Result<(), Error> {
+        let command = PlayerCommand::Graduate(GraduateParam { token: self.token });
+        match self.send(&command).await? {
+            CommandResult::Spawn(_) => Ok(()),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    /// ごはんが出る、正面に見えるものが変わる、ポイントが変わるのどれかが起きるまで待つ。
+    /// `timeout` (ms) を省略すると 10 秒でタイムアウトする
+    pub async fn wait_for(&mut self, timeout: Option<u64>) -> Result<WaitForResult, Error> {
+        let command = PlayerCommand::WaitFor(WaitForParam {
+            token: self.token,
+            timeout,
+        });
+        match self.send(&command).await? {
+            CommandResult::WaitFor(result) => Ok(result),
+            result => Err(Error::UnexpectedResult(result)),
+        }
+    }
+
+    async fn send(&mut self, command: &PlayerCommand) -> Result<CommandResult, Error> {
+        match self.transport.send(command).await? {
+            CommandResult::CrabNotFound => Err(Error::CrabNotFound),
+            CommandResult::OutOfEnergy => Err(Error::OutOfEnergy),
+            CommandResult::StageNotFound => Err(Error::StageNotFound),
+            CommandResult::CommandNotAllowed => Err(Error::CommandNotAllowed),
+            CommandResult::SandboxNotFound => Err(Error::SandboxNotFound),
+            result => Ok(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// 決まった結果を順に返し、送られたコマンドを記録するだけの Transport
+    #[derive(Default)]
+    struct MockTransport {
+        results: VecDeque<CommandResult>,
+        sent: Vec<PlayerCommand>,
+    }
+
+    impl Transport for MockTransport {
+        async fn send(&mut self, command: &PlayerCommand) -> Result<CommandResult, Error> {
+            self.sent.push(command.clone());
+            self.results.pop_front().ok_or(Error::Closed)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crab_maps_results() {
+        let token = Token::new();
+        let transport = MockTransport {
+            results: VecDeque::from([
+                CommandResult::Walk(WalkResult {
+                    success: true,
+                    point: 1,
+                    total_point: 3,
+                    energy: None,
+                }),
+                CommandResult::Scan(ScanResult {
+                    what_you_can_see: WhatYouCanSee::Wall,
+                    food_kind: None,
+                    team: None,
+                }),
+                CommandResult::Turn,
+                CommandResult::CrabNotFound,
+            ]),
+            ..Default::default()
+        };
+        let mut crab = Crab::with_token(transport, token);
+
+        let walk = crab.walk(Side::Left).await.unwrap();
+        assert!(walk.success);
+        assert_eq!(walk.total_point, 3);
+        assert_eq!(crab.scan().await.unwrap(), WhatYouCanSee::Wall);
+        // コマンドに対応しない結果はエラーになる
+        assert!(matches!(
+            crab.paint().await,
+            Err(Error::UnexpectedResult(CommandResult::Turn))
+        ));
+        assert!(matches!(
+            crab.walk(Side::Right).await,
+            Err(Error::CrabNotFound)
+        ));
+
+        let sent = &crab.transport.sent;
+        assert!(matches!(
+            &sent[0],
+            PlayerCommand::Walk(WalkParam { token: t, side: Side::Left }) if *t == token
+        ));
+        assert!(matches!(&sent[1], PlayerCommand::Scan(ScanParam { token: t }) if *t == token));
+        assert!(matches!(&sent[2], PlayerCommand::Paint(_)));
+    }
+
+    #[test]
+    fn test_parse_server_error() {
+        assert!(matches!(
+            transport::parse_result(r#"{"error": "invalid command: x"}"#),
+            Err(Error::Server(message)) if message == "invalid command: x"
+        ));
+        assert!(matches!(
+            transport::parse_result(r#"{"type": "Turn"}"#),
+            Ok(CommandResult::Turn)
+        ));
+        assert!(matches!(
+            transport::parse_result("not json"),
+            Err(Error::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{CommandResult, Error, PlayerCommand};
+use futures::{SinkExt, StreamExt};
+use std::future::Future;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// コマンドをサーバーに届ける方法
+pub trait Transport {
+    fn send(
+        &mut self,
+        command: &PlayerCommand,
+    ) -> impl Future<Output = Result<CommandResult, Error>> + Send;
+}
+
+/// `POST /api/command` にコマンドごとにリクエストする
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpTransport {
+    /// `origin` はサーバーのオリジン (例: `http://localhost:8000`)
+    pub fn new(origin: &str) -> Self {
+        HttpTransport {
+            client: reqwest::Client::new(),
+            url: format!("{}/api/command", origin.trim_end_matches('/')),
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    async fn send(&mut self, command: &PlayerCommand) -> Result<CommandResult, Error> {
+        let response = self.client.post(&self.url).json(command).send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            // 本文にエラーの理由があれば、それを返す
+            let message = match server_error(&text) {
+                Some(message) => message,
+                None if text.is_empty() => status.to_string(),
+                None => text,
+            };
+            return Err(Error::Server(message));
+        }
+        parse_result(&text)
+    }
+}
+
+/// `/api/ws` の WebSocket をつなぎっぱなしにしてコマンドを送る
+#[derive(Debug)]
+pub struct SocketTransport {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl SocketTransport {
+    /// `origin` はサーバーのオリジン (例: `http://localhost:8000`)
+    pub async fn connect(origin: &str) -> Result<Self, Error> {
+        let origin = origin.trim_end_matches('/');
+        let origin = origin
+            .strip_prefix("https://")
+            .map(|host| format!("wss://{}", host))
+            .or_else(|| {
+                origin
+                    .strip_prefix("http://")
+                    .map(|host| format!("ws://{}", host))
+            })
+            .unwrap_or_else(|| origin.to_string());
+        let (stream, _) = tokio_tungstenite::connect_async(format!("{}/api/ws", origin)).await?;
+        Ok(SocketTransport { stream })
+    }
+}
+
+impl Transport for SocketTransport {
+    async fn send(&mut self, command: &PlayerCommand) -> Result<CommandResult, Error> {
+        let text = serde_json::to_string(command)?;
+        self.stream.send(Message::Text(text)).await?;
+        while let Some(message) = self.stream.next().await {
+            match message? {
+                Message::Text(text) => return parse_result(&text),
+                Message::Close(_) => break,
+                _ => continue,
+            }
+        }
+        Err(Error::Closed)
+    }
+}
+
+/// サーバーからの返事を読む。`{"error": "..."}` ならサーバーのエラー、そうでなければコマンドの結果
+pub(crate) fn parse_result(text: &str) -> Result<CommandResult, Error> {
+    if let Some(message) = server_error(text) {
+        return Err(Error::Server(message));
+    }
+    Ok(serde_json::from_str(text)?)
+}
+
+fn server_error(text: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    Some(value.get("error")?.as_str()?.to_string())
+}
@@ -1,11 +1,13 @@
 #[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
-pub(crate) enum Side {
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Side {
     Right,
     Left,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
-pub(crate) enum Direction {
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Direction {
     N,
     E,
     S,
@@ -13,14 +15,14 @@ pub(crate) enum Direction {
 }
 
 impl Direction {
-    pub(crate) fn turn(self, side: Side) -> Direction {
+    pub fn turn(self, side: Side) -> Direction {
         match side {
             Side::Right => self.right(),
             Side::Left => self.left(),
         }
     }
 
-    pub(crate) fn right(self) -> Direction {
+    pub fn right(self) -> Direction {
         match self {
             Direction::N => Direction::E,
             Direction::E => Direction::S,
@@ -29,7 +31,7 @@ impl Direction {
         }
     }
 
-    pub(crate) fn left(self) -> Direction {
+    pub fn left(self) -> Direction {
         match self {
             Direction::N => Direction::W,
             Direction::E => Direction::N,
@@ -38,9 +40,8 @@ impl Direction {
         }
     }
 
-    pub(crate) fn random() -> Direction {
-        use rand::Rng;
-        match rand::thread_rng().gen_range(0..4) {
+    pub fn random(rng: &mut (impl rand::Rng + ?Sized)) -> Direction {
+        match rng.gen_range(0..4) {
             0 => Direction::N,
             1 => Direction::E,
             2 => Direction::S,
@@ -50,27 +51,26 @@ impl Direction {
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
-pub(crate) struct Position {
-    x: i32,
-    y: i32,
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Position {
-    #[cfg(test)]
-    pub(crate) fn new(x: i32, y: i32) -> Position {
+    pub fn new(x: i32, y: i32) -> Position {
         Position { x, y }
     }
 
-    pub(crate) fn random(width: u32, height: u32) -> Position {
-        use rand::Rng;
+    pub fn random(rng: &mut (impl rand::Rng + ?Sized), width: u32, height: u32) -> Position {
         Position {
-            x: rand::thread_rng().gen_range(0..width as i32),
-            y: rand::thread_rng().gen_range(0..height as i32),
+            x: rng.gen_range(0..width as i32),
+            y: rng.gen_range(0..height as i32),
         }
     }
 
     /// 向いている方向に対し横(side)方向に移動する
-    pub(crate) fn walk(&self, direction: Direction, side: Side) -> Position {
+    pub fn walk(&self, direction: Direction, side: Side) -> Position {
         match side {
             Side::Right => self.move_right(direction),
             Side::Left => self.move_left(direction),
@@ -78,7 +78,7 @@ impl Position {
     }
 
     /// 向いている方向に進む
-    pub(crate) fn forward(&self, direction: Direction) -> Position {
+    pub fn forward(&self, direction: Direction) -> Position {
         match direction {
             Direction::N => Position {
                 y: self.y - 1,
@@ -99,7 +99,7 @@ impl Position {
         }
     }
 
-    pub(crate) fn move_right(&self, direction: Direction) -> Position {
+    pub fn move_right(&self, direction: Direction) -> Position {
         match direction {
             Direction::N => Position {
                 x: self.x + 1,
@@ -120,7 +120,7 @@ impl Position {
         }
     }
 
-    pub(crate) fn move_left(&self, direction: Direction) -> Position {
+    pub fn move_left(&self, direction: Direction) -> Position {
         match direction {
             Direction::N => Position {
                 x: self.x - 1,
@@ -141,7 +141,7 @@ impl Position {
         }
     }
 
-    pub(crate) fn is_inset(&self, width: i32, height: i32) -> bool {
+    pub fn is_inset(&self, width: i32, height: i32) -> bool {
         self.x >= 0 && self.x < width && self.y >= 0 && self.y < height
     }
 }
@@ -0,0 +1,9 @@
+//! kani-life のサーバーとクライアントで共有するコマンドの型
+
+pub mod geometry;
+pub mod player_command;
+pub mod token;
+
+pub use geometry::{Direction, Position, Side};
+pub use player_command::{CommandResult, PlayerCommand};
+pub use token::Token;
@@ -0,0 +1,361 @@
+use crate::geometry::{Position, Side};
+use crate::token::Token;
+
+/// プレイヤーがカニを操作するコマンド
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "type")]
+pub enum PlayerCommand {
+    /// サーバーとの接続確認
+    Ping,
+    /// 自カニを出現させる
+    Spawn(SpawnParam),
+    /// 正面になにが見えるか調べる
+    Scan(ScanParam),
+    /// その場で左右に回転する
+    Turn(TurnParam),
+    /// 左右に1マス移動する
+    Walk(WalkParam),
+    /// 今いるマスを塗る
+    Paint(PaintParam),
+    /// 正面のカニをはさむ
+    Pinch(PinchParam),
+    /// カニをフィールドから出して、ひとりで遊ぶパズルのステージを (はじめから) 始める
+    StartStage(StartStageParam),
+    /// 遊んでいるステージの進み具合を調べる
+    StageStatus(StageStatusParam),
+    /// 練習用のサンドボックスをはじめからやり直す
+    ResetSandbox(ResetSandboxParam),
+    /// 練習用のサンドボックスを卒業して、みんなのフィールドに出る
+    Graduate(GraduateParam),
+    /// ごはんが出る、正面に見えるものが変わる、ポイントが変わるのどれかが起きるまで結果を返さずに待つ
+    WaitFor(WaitForParam),
+}
+
+impl PlayerCommand {
+    /// コマンドの種類名 (`type` タグと同じ)
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayerCommand::Ping => "Ping",
+            PlayerCommand::Spawn(_) => "Spawn",
+            PlayerCommand::Scan(_) => "Scan",
+            PlayerCommand::Turn(_) => "Turn",
+            PlayerCommand::Walk(_) => "Walk",
+            PlayerCommand::Paint(_) => "Paint",
+            PlayerCommand::Pinch(_) => "Pinch",
+            PlayerCommand::StartStage(_) => "StartStage",
+            PlayerCommand::StageStatus(_) => "StageStatus",
+            PlayerCommand::ResetSandbox(_) => "ResetSandbox",
+            PlayerCommand::Graduate(_) => "Graduate",
+            PlayerCommand::WaitFor(_) => "WaitFor",
+        }
+    }
+
+    /// 操作対象のカニのトークン。Ping と Spawn は持たない
+    pub fn token(&self) -> Option<Token> {
+        match self {
+            PlayerCommand::Ping | PlayerCommand::Spawn(_) => None,
+            PlayerCommand::Scan(param) => Some(param.token),
+            PlayerCommand::Turn(param) => Some(param.token),
+            PlayerCommand::Walk(param) => Some(param.token),
+            PlayerCommand::Paint(param) => Some(param.token),
+            PlayerCommand::Pinch(param) => Some(param.token),
+            PlayerCommand::StartStage(param) => Some(param.token),
+            PlayerCommand::StageStatus(param) => Some(param.token),
+            PlayerCommand::ResetSandbox(param) => Some(param.token),
+            PlayerCommand::Graduate(param) => Some(param.token),
+            PlayerCommand::WaitFor(param) => Some(param.token),
+        }
+    }
+}
+
+/// コマンドの結果
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "type")]
+pub enum CommandResult {
+    /// 結果を持たないコマンドの成功
+    Ok,
+    /// Ping に対する応答
+    Pong,
+    /// まだ実装されていないコマンド
+    NotImplemented,
+    /// token に一致するカニが見つからなかったときのエラー
+    CrabNotFound,
+    /// エネルギーが 0 で行動できなかったときのエラー
+    OutOfEnergy,
+    /// 指定した ID のステージが見つからなかったときのエラー
+    StageNotFound,
+    /// 遊んでいるステージで使えないコマンドや、動けない間に行動するコマンドを送ったときのエラー
+    CommandNotAllowed,
+    /// サンドボックスにいないカニで ResetSandbox や Graduate を送ったときのエラー
+    SandboxNotFound,
+    Spawn(SpawnResult),
+    Scan(ScanResult),
+    Turn,
+    Walk(WalkResult),
+    Paint(PaintResult),
+    Pinch(PinchResult),
+    StageStatus(StageStatusResult),
+    WaitFor(WaitForResult),
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SpawnParam {
+    /// カニ名。同じ名前のカニは1つまで召喚できる
+    pub name: String,
+    /// カニの色相。チームに入る場合はチームの色になる
+    pub hue: f32,
+    /// 入るチーム名。同じチームのカニはポイントとペイントの色を共有する
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    /// 指定すると、みんなのフィールドではなく自分だけの練習用サンドボックスに出現する
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxMode>,
+}
+
+impl SpawnParam {
+    /// チームに入らず、みんなのフィールドに出現させる
+    pub fn new(name: &str, hue: f32) -> Self {
+        SpawnParam {
+            name: name.to_string(),
+            hue,
+            team: None,
+            sandbox: None,
+        }
+    }
+}
+
+/// サンドボックスでのコマンドの結果の返し方
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum SandboxMode {
+    /// 待たずにすぐ返す
+    Fast,
+    /// 1 コマンドずつ追えるように、毎回 1 秒待ってから返す
+    Step,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SpawnResult {
+    /// 出現したカニを操作するためのトークン
+    pub token: Token,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ScanParam {
+    pub token: Token,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResult {
+    pub what_you_can_see: WhatYouCanSee,
+    /// 見えたごはんの種類。ごはんの種類を明かすルールのときだけ返す
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub food_kind: Option<FoodKind>,
+    /// 見えた旗や陣地のチーム名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum WhatYouCanSee {
+    Food,
+    Crab,
+    Wall,
+    /// キャプチャー・ザ・フラッグの旗
+    Flag,
+    /// キャプチャー・ザ・フラッグの陣地
+    Base,
+    /// キング・オブ・ザ・ヒルの丘
+    Hill,
+}
+
+/// ごはんの種類
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum FoodKind {
+    /// サイズと同じポイントがもらえる
+    #[default]
+    Normal,
+    /// サイズの 3 倍のポイントがもらえる
+    Golden,
+    /// 食べるとポイントが減るか、しばらく動けなくなる
+    Poison,
+    /// 時間が経つと小さくなり、最後には消えてしまう
+    Rotting,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct TurnParam {
+    pub token: Token,
+    pub side: Side,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct WalkParam {
+    pub token: Token,
+    pub side: Side,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct WalkResult {
+    /// 移動に成功したか
+    pub success: bool,
+    /// ゲットしたごはんポイント
+    pub point: i32,
+    /// トータルのごはんポイント
+    pub total_point: i32,
+    /// 残りのエネルギー。エネルギーのルールのときだけ返す
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<u32>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PaintParam {
+    pub token: Token,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PaintResult {
+    /// 成功したか (ポイントがない場合は失敗する)
+    pub success: bool,
+    /// 現在塗れているマスの配列
+    pub your_paints: Vec<Position>,
+    /// トータルのごはんポイント
+    pub total_point: i32,
+    /// 残りのエネルギー。エネルギーのルールのときだけ返す
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<u32>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PinchParam {
+    pub token: Token,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PinchResult {
+    /// 正面のカニをはさめたか (正面にカニがいない場合や、はさむのが禁止されているルームでは失敗する)
+    pub success: bool,
+    /// 奪ったごはんポイント
+    pub stolen: i32,
+    /// トータルのごはんポイント
+    pub total_point: i32,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct StartStageParam {
+    pub token: Token,
+    /// ステージの ID
+    pub stage: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct StageStatusParam {
+    pub token: Token,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct StageStatusResult {
+    /// ステージの ID
+    pub stage: String,
+    pub progress: StageProgress,
+    /// 使ったステップ (コマンド) の数
+    pub steps: u32,
+    /// 使えるステップの数
+    pub step_budget: u32,
+    /// 失敗したときや、ステップを半分以上使ったときのヒント
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+/// ステージの進み具合
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum StageProgress {
+    Playing,
+    /// ゴールした
+    Passed,
+    /// ゴールする前にステップを使い切った
+    Failed,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ResetSandboxParam {
+    pub token: Token,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GraduateParam {
+    pub token: Token,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct WaitForParam {
+    pub token: Token,
+    /// 待つ最大の時間 (ms)。省略すると 10 秒。60 秒より長くは待たない
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct WaitForResult {
+    /// 待つのをやめた理由
+    pub reason: WaitReason,
+    /// 今、正面に見えるもの (Scan の結果と同じ)
+    pub scan: ScanResult,
+    /// トータルのごはんポイント
+    pub total_point: i32,
+}
+
+/// WaitFor で待つのをやめた理由
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum WaitReason {
+    /// フィールドのどこかにごはんが出た
+    FoodSpawned,
+    /// 正面に見えるものが変わった
+    ScanChanged,
+    /// ポイントが変わった
+    PointChanged,
+    /// 何も起きないまま時間が過ぎた
+    Timeout,
+}
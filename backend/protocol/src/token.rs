@@ -11,6 +11,12 @@ impl Token {
     }
 }
 
+impl Default for Token {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.0.fmt(f)
@@ -46,6 +52,19 @@ impl<'de> serde::Deserialize<'de> for Token {
     }
 }
 
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Token {
+    fn schema_name() -> String {
+        "Token".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        let mut schema = gen.subschema_for::<String>().into_object();
+        schema.format = Some("uuid".to_string());
+        schema.into()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,278 @@
+//! 進行役のダッシュボード向けに、カニごとの活動を集めて配信する
+//!
+//! command_processor で処理したコマンドと結果をカニごとに記録し、socket.io の `/admin` 名前空間に
+//! 定期的に `activity` イベントで配信する。しばらく止まっている参加者や、壁に向かって歩き続けている
+//! 参加者を見つけやすくするためのもの。接続するときは auth に `{ "key": "<KANI_ADMIN_KEY>" }` を付ける
+
+use crate::admin::SocketAuth;
+use crate::command::game_cycle_command::GameCycleCommand;
+use crate::command::player_command::{PlayerCommand, StageStatusResult};
+use crate::command::{Command, CommandResult};
+use crate::game_state::GameState;
+use crate::history::Pose;
+use crate::token::Token;
+use socketioxide::extract::{SocketRef, TryData};
+use socketioxide::SocketIo;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// この時間 (ms) コマンドが来ていなければ止まっているとみなす
+const IDLE_AFTER: u64 = 5 * 60 * 1000;
+/// コマンドの頻度を数える期間 (ms)
+const RATE_WINDOW: u64 = 60 * 1000;
+/// 失敗した Walk がこの回数続いたら、歩けずに困っているとみなす
+const FAILING_WALKS: usize = 5;
+/// `/admin` 名前空間に配信する間隔
+const BROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+
+/// カニ 1 匹分の活動
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CrabActivity {
+    /// カニ名。Spawn を見ていないトークンではトークンの文字列
+    pub name: String,
+    /// 最後にコマンドを処理した時刻 (ms)
+    pub last_command_at: u64,
+    pub last_command: &'static str,
+    /// 最後のエラー (`CrabNotFound` や失敗した Walk など) と、その時刻 (ms)
+    pub last_error: Option<&'static str>,
+    pub last_error_at: Option<u64>,
+    /// 直近 1 分間のコマンド数
+    pub commands_per_minute: usize,
+    /// 遊んでいるステージの進み具合
+    pub stage: Option<StageStatusResult>,
+    /// 5 分以上コマンドが来ていない
+    pub idle: bool,
+    /// 失敗した Walk ばかり続いている
+    pub only_failing_walks: bool,
+    /// 直近 1 分間にコマンドを処理した時刻 (ms)
+    #[serde(skip)]
+    recent: VecDeque<u64>,
+    /// 続けて失敗した Walk の数
+    #[serde(skip)]
+    failing_walks: usize,
+}
+
+impl CrabActivity {
+    fn new(name: String) -> Self {
+        CrabActivity {
+            name,
+            last_command_at: 0,
+            last_command: "",
+            last_error: None,
+            last_error_at: None,
+            commands_per_minute: 0,
+            stage: None,
+            idle: false,
+            only_failing_walks: false,
+            recent: VecDeque::new(),
+            failing_walks: 0,
+        }
+    }
+
+    fn record(&mut self, command: &'static str, result: &CommandResult, now: u64) {
+        self.last_command_at = now;
+        self.last_command = command;
+        self.recent.push_back(now);
+        if let Some(error) = error_label(result) {
+            self.last_error = Some(error);
+            self.last_error_at = Some(now);
+        }
+        match result {
+            CommandResult::Walk(result) if !result.success => self.failing_walks += 1,
+            _ => self.failing_walks = 0,
+        }
+    }
+
+    /// `now` の時点の頻度とフラグを計算する
+    fn refresh(&mut self, now: u64) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|at| at + RATE_WINDOW <= now)
+        {
+            self.recent.pop_front();
+        }
+        self.commands_per_minute = self.recent.len();
+        self.idle = now.saturating_sub(self.last_command_at) >= IDLE_AFTER;
+        self.only_failing_walks = self.failing_walks >= FAILING_WALKS;
+    }
+}
+
+/// 参加者がつまずいたとみなす結果
+fn error_label(result: &CommandResult) -> Option<&'static str> {
+    match result {
+        CommandResult::CrabNotFound => Some("CrabNotFound"),
+        CommandResult::OutOfEnergy => Some("OutOfEnergy"),
+        CommandResult::StageNotFound => Some("StageNotFound"),
+        CommandResult::CommandNotAllowed => Some("CommandNotAllowed"),
+        CommandResult::SandboxNotFound => Some("SandboxNotFound"),
+        CommandResult::NotImplemented => Some("NotImplemented"),
+        CommandResult::Walk(result) if !result.success => Some("WalkFailed"),
+        CommandResult::Paint(result) if !result.success => Some("PaintFailed"),
+        CommandResult::Pinch(result) if !result.success => Some("PinchFailed"),
+        _ => None,
+    }
+}
+
+/// カニごとの活動。トークンごとに記録する
+#[derive(Debug, Default)]
+pub(crate) struct Activity {
+    crabs: HashMap<Token, CrabActivity>,
+}
+
+impl Activity {
+    /// command_processor で処理したプレイヤーのコマンドと、NPC の出現を記録する。
+    /// でたらめなトークンで記録が増え続けないように、処理の前後ともカニがいないトークンは記録しない
+    pub(crate) fn record(
+        &mut self,
+        command: &Command,
+        before: Option<Pose>,
+        result: &CommandResult,
+        now: u64,
+        state: &GameState,
+    ) {
+        let (name, token) = match (command, result) {
+            (
+                Command::PlayerCommand(PlayerCommand::Spawn(param))
+                | Command::GameCycleCommand(GameCycleCommand::SpawnNpc(param)),
+                CommandResult::Spawn(result),
+            ) => {
+                // Spawn し直したカニは、同じ名前の記録を新しいトークンに引き継ぐ
+                let old = self.crabs.iter().find(|(_, a)| a.name == param.name);
+                let old = old.map(|(token, _)| *token);
+                let activity = old
+                    .and_then(|token| self.crabs.remove(&token))
+                    .unwrap_or_else(|| CrabActivity::new(param.name.clone()));
+                self.crabs.insert(result.token, activity);
+                ("Spawn", result.token)
+            }
+            (Command::PlayerCommand(command), _) => match command.token() {
+                Some(token) => (command.name(), token),
+                None => return,
+            },
+            (Command::GameCycleCommand(_), _) => return,
+        };
+        if before.is_none() && state.pose(&token).is_none() && !self.crabs.contains_key(&token) {
+            return;
+        }
+        let activity = self
+            .crabs
+            .entry(token)
+            .or_insert_with(|| CrabActivity::new(token.to_string()));
+        activity.record(name, result, now);
+        activity.stage = state.stage_progress(&token);
+    }
+
+    /// `now` の時点の全カニの活動。カニ名の順
+    pub(crate) fn snapshot(&mut self, now: u64) -> Vec<CrabActivity> {
+        let mut crabs: Vec<CrabActivity> = self
+            .crabs
+            .values_mut()
+            .map(|activity| {
+                activity.refresh(now);
+                activity.clone()
+            })
+            .collect();
+        crabs.sort_by(|a, b| a.name.cmp(&b.name));
+        crabs
+    }
+}
+
+/// `/admin` 名前空間を用意し、活動を定期的に配信する。`KANI_ADMIN_KEY` が未設定なら接続できない
+pub(crate) fn serve(
+    io: &SocketIo,
+    activity: Arc<Mutex<Activity>>,
+    admin_key: Option<String>,
+    started_at: Instant,
+) {
+    io.ns(
+        "/admin",
+        move |s: SocketRef, TryData(auth): TryData<SocketAuth>| {
+            // auth がないか形が違うときも、接続したままにせず切断する
+            if !auth.is_ok_and(|auth| auth.is_admin(&admin_key)) {
+                tracing::warn!("Rejected admin socket");
+                s.disconnect().ok();
+            }
+        },
+    );
+    let io = io.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(BROADCAST_INTERVAL).await;
+            let now = started_at.elapsed().as_millis() as u64;
+            let snapshot = activity.lock().await.snapshot(now);
+            let Some(admin) = io.of("/admin") else {
+                continue;
+            };
+            if admin.emit("activity", snapshot).is_err() {
+                tracing::error!("Failed to emit activity");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::player_command::{SpawnParam, SpawnResult, WalkParam, WalkResult};
+    use crate::geometry::Side;
+
+    #[test]
+    fn test_flags() {
+        let state = GameState::new(5);
+        let mut activity = Activity::default();
+        let token = Token::new();
+        let spawn = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam::new("a", 0.0)));
+        let spawned = CommandResult::Spawn(SpawnResult { token });
+        activity.record(&spawn, None, &spawned, 0, &state);
+        let walk = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token,
+            side: Side::Right,
+        }));
+        let failed = CommandResult::Walk(WalkResult {
+            success: false,
+            point: 0,
+            total_point: 0,
+            energy: None,
+        });
+        for i in 1..=FAILING_WALKS as u64 {
+            activity.record(&walk, None, &failed, i * 1000, &state);
+        }
+        let crab = &activity.snapshot(10_000)[0];
+        assert_eq!(crab.name, "a");
+        assert_eq!(crab.commands_per_minute, 6);
+        assert_eq!(crab.last_error, Some("WalkFailed"));
+        assert!(crab.only_failing_walks);
+        assert!(!crab.idle);
+
+        // 5 分以上コマンドが来なければ止まっているとみなす
+        let crab = &activity.snapshot(5000 + IDLE_AFTER)[0];
+        assert_eq!(crab.commands_per_minute, 0);
+        assert!(crab.idle);
+
+        // カニがいないトークンのコマンドは記録しない
+        let stray = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token: Token::new(),
+            side: Side::Right,
+        }));
+        activity.record(&stray, None, &CommandResult::CrabNotFound, 6000, &state);
+        assert_eq!(activity.snapshot(6000).len(), 1);
+
+        // NPC はトークンではなく名前で記録する
+        let npc =
+            Command::GameCycleCommand(GameCycleCommand::SpawnNpc(SpawnParam::new("npc", 0.0)));
+        let spawned = CommandResult::Spawn(SpawnResult {
+            token: Token::new(),
+        });
+        activity.record(&npc, None, &spawned, 7000, &state);
+        let names: Vec<String> = activity
+            .snapshot(7000)
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, ["a", "npc"]);
+    }
+}
@@ -0,0 +1,107 @@
+//! 進行役が使う操作
+//!
+//! `KANI_ADMIN_KEY` を設定したときだけ使える。リクエストには `x-admin-key` ヘッダーでキーを付ける
+
+use crate::command::game_cycle_command::GameCycleCommand;
+use crate::command::Command;
+use crate::{enqueue_command, GameCommandCase};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use subtle::ConstantTimeEq;
+use tokio::sync::mpsc;
+
+/// socket.io に接続するときの auth (`{ "key": "<KANI_ADMIN_KEY>" }`)。`?key=` のクエリにも使う
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct SocketAuth {
+    key: Option<String>,
+}
+
+impl SocketAuth {
+    /// 進行役のキーを持っているか。キーが未設定なら誰も持っていない
+    pub(crate) fn is_admin(&self, admin_key: &Option<String>) -> bool {
+        match (admin_key, &self.key) {
+            (Some(admin_key), Some(key)) => key_matches(admin_key, key.as_bytes()),
+            _ => false,
+        }
+    }
+
+    /// フィールド全体にかかわる情報を見てよいか。霧の中モードでは進行役のキーを持っているときだけ
+    pub(crate) fn can_see_field(&self, admin_key: &Option<String>, fog_of_war: bool) -> bool {
+        !fog_of_war || self.is_admin(admin_key)
+    }
+}
+
+/// キーを比べる。一致するまでの時間からキーを推測されないように、定数時間で比べる
+fn key_matches(admin_key: &str, key: &[u8]) -> bool {
+    admin_key.as_bytes().ct_eq(key).into()
+}
+
+#[derive(Clone)]
+struct AdminState {
+    command_tx: mpsc::Sender<GameCommandCase>,
+    admin_key: Option<String>,
+}
+
+impl AdminState {
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), StatusCode> {
+        let Some(key) = &self.admin_key else {
+            return Err(StatusCode::NOT_FOUND);
+        };
+        match headers.get("x-admin-key") {
+            Some(value) if key_matches(key, value.as_bytes()) => Ok(()),
+            _ => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+}
+
+pub(crate) fn router(
+    command_tx: mpsc::Sender<GameCommandCase>,
+    admin_key: Option<String>,
+) -> Router {
+    Router::new()
+        .route("/api/admin/frenzy", post(post_frenzy))
+        .route("/api/admin/paint-battle", post(post_paint_battle))
+        .with_state(AdminState {
+            command_tx,
+            admin_key,
+        })
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct FrenzyQuery {
+    /// 大量発生させる時間 (秒)
+    duration: Option<u64>,
+}
+
+/// ごはんを大量発生させる
+async fn post_frenzy(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Query(query): Query<FrenzyQuery>,
+) -> StatusCode {
+    if let Err(status) = state.authorize(&headers) {
+        return status;
+    }
+    let duration = query.duration.unwrap_or(30).saturating_mul(1000);
+    let root = tracing::span!(parent: None, tracing::Level::TRACE, "feeding_frenzy", duration);
+    let command = Command::GameCycleCommand(GameCycleCommand::FeedingFrenzy { duration });
+    match enqueue_command(&state.command_tx, command, root).await {
+        Some(_) => StatusCode::NO_CONTENT,
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// ペイントバトルの試合をはじめからやり直す。ペイントはすべて消える
+async fn post_paint_battle(State(state): State<AdminState>, headers: HeaderMap) -> StatusCode {
+    if let Err(status) = state.authorize(&headers) {
+        return status;
+    }
+    let root = tracing::span!(parent: None, tracing::Level::TRACE, "start_paint_battle");
+    let command = Command::GameCycleCommand(GameCycleCommand::StartPaintBattle);
+    match enqueue_command(&state.command_tx, command, root).await {
+        Some(_) => StatusCode::NO_CONTENT,
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
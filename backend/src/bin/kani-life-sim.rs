@@ -0,0 +1,93 @@
+//! サーバーを立てずにボット同士を何度も対戦させ、成績を表示する
+//!
+//! ```text
+//! cargo run --bin kani-life-sim -- --games 100 --bots greedy,painter,random,wall
+//! ```
+
+use clap::Parser;
+use kani_life::simulator::Simulator;
+use kani_life::strategy;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Parser)]
+struct Args {
+    /// 対戦させる戦略 (random, greedy, painter, wall)。同じ戦略を何度指定してもよい
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "random,greedy,painter,wall"
+    )]
+    bots: Vec<String>,
+    /// 対戦回数
+    #[arg(long, default_value_t = 100)]
+    games: u64,
+    /// 最初のゲームのシード。n 回目のゲームは seed + n で回す
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// フィールドの大きさ
+    #[arg(long, default_value_t = 30)]
+    size: u32,
+    /// 1 ゲームの長さ (ゲーム内時間の秒)
+    #[arg(long, default_value_t = 300)]
+    duration: u64,
+}
+
+/// ボットごとの通算成績
+#[derive(Debug, Default)]
+struct Stats {
+    wins: u64,
+    rank: u64,
+    point: i64,
+    paints: u64,
+}
+
+fn main() -> Result<(), String> {
+    let args = Args::parse();
+    // 同じ戦略が複数いても区別できるように番号をつける
+    let names: Vec<(String, String)> = args
+        .bots
+        .iter()
+        .enumerate()
+        .map(|(i, bot)| (format!("{}-{}", bot, i + 1), bot.clone()))
+        .collect();
+    if let Some((_, unknown)) = names.iter().find(|(_, s)| strategy::by_name(s).is_none()) {
+        return Err(format!("unknown strategy: {}", unknown));
+    }
+
+    let mut stats: BTreeMap<String, Stats> = BTreeMap::new();
+    for game in 0..args.games {
+        let mut simulator = Simulator::new(args.size, args.seed.wrapping_add(game));
+        for (name, bot) in &names {
+            simulator.add_bot(name, strategy::by_name(bot).unwrap());
+        }
+        for score in simulator.run(args.duration * 1000).ranking {
+            let stats = stats.entry(score.name.clone()).or_default();
+            if score.rank == 1 {
+                stats.wins += 1;
+            }
+            stats.rank += score.rank as u64;
+            stats.point += score.point as i64;
+            stats.paints += score.paints as u64;
+        }
+    }
+
+    let games = args.games.max(1) as f64;
+    println!(
+        "{:<16} {:>6} {:>9} {:>10} {:>10} {:>10}",
+        "bot", "wins", "avg rank", "avg point", "avg paints", "avg score"
+    );
+    let mut rows: Vec<_> = stats.into_iter().collect();
+    rows.sort_by_key(|(_, s)| s.rank);
+    for (name, s) in rows {
+        println!(
+            "{:<16} {:>6} {:>9.2} {:>10.2} {:>10.2} {:>10.2}",
+            name,
+            s.wins,
+            s.rank as f64 / games,
+            s.point as f64 / games,
+            s.paints as f64 / games,
+            (s.point + s.paints as i64) as f64 / games,
+        );
+    }
+    Ok(())
+}
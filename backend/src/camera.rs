@@ -0,0 +1,55 @@
+//! 観戦用のカメラ
+//!
+//! 大きなフィールドでもプロジェクターや小さな画面で見られるように、1 匹のカニのまわりだけを切り出す。
+//! 追いかけるのは名前で指定したカニか、直前にポイントを取ったカニ (ハイライト)
+
+use crate::game_state::PublicState;
+use crate::geometry::Position;
+
+/// カメラが追いかけるもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Camera {
+    /// 名前で指定したカニ
+    Follow(String),
+    /// 直前にポイントを取ったカニ。まだ誰も取っていなければ一番ポイントの多いカニ
+    Highlights,
+}
+
+/// フィールドから切り出す正方形の範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Viewport {
+    pub left: i32,
+    pub top: i32,
+    pub size: u32,
+}
+
+impl Viewport {
+    /// `center` のまわり `radius` マスの範囲。フィールドからはみ出さないようにずらす
+    pub(crate) fn around(center: Position, radius: u32, field_size: u32) -> Self {
+        let size = (radius * 2 + 1).min(field_size);
+        let far = (field_size - size) as i32;
+        Viewport {
+            left: (center.x - radius as i32).clamp(0, far),
+            top: (center.y - radius as i32).clamp(0, far),
+            size,
+        }
+    }
+
+    pub(crate) fn contains(&self, position: &Position) -> bool {
+        let size = self.size as i32;
+        (self.left..self.left + size).contains(&position.x)
+            && (self.top..self.top + size).contains(&position.y)
+    }
+}
+
+/// カメラに映るフィールド。`state` イベントと同じ形に、追いかけているカニと範囲を加える。
+/// 範囲の外のカニやごはん、ペイントは含めない (位置はフィールドの座標のまま)
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct View<'a> {
+    pub following: &'a str,
+    pub viewport: Viewport,
+    #[serde(flatten)]
+    pub(crate) state: PublicState<'a>,
+}
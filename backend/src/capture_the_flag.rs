@@ -0,0 +1,217 @@
+//! キャプチャー・ザ・フラッグ (旗取り) モード
+//!
+//! チームごとに陣地と旗がある。相手の旗の上を歩くと旗を持ち、
+//! 自分の旗が陣地にある間に相手の旗を自分の陣地まで持ち帰ると得点になる。
+//! 得点と旗を元に戻す処理は game_cycle の Tick で行う
+
+use crate::crab::Crab;
+use crate::geometry::Position;
+use crate::token::Token;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// チームの陣地。中心のまわり 3x3 マス
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Base {
+    pub team: String,
+    pub center: Position,
+    /// 相手の旗を持ち帰った回数
+    pub captures: u32,
+}
+
+impl Base {
+    pub(crate) fn contains(&self, position: &Position) -> bool {
+        (position.x - self.center.x).abs() <= 1 && (position.y - self.center.y).abs() <= 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Flag {
+    pub team: String,
+    pub position: Position,
+    /// 旗を持っているカニの名前
+    pub carried_by: Option<String>,
+    #[serde(skip)]
+    carrier: Option<Token>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CaptureTheFlag {
+    bases: Vec<Base>,
+    flags: Vec<Flag>,
+}
+
+impl CaptureTheFlag {
+    fn base(&self, team: &str) -> Option<&Base> {
+        self.bases.iter().find(|b| b.team == team)
+    }
+
+    /// `team` のカニが入れるか。新しいチームの陣地を置く場所がなければ入れない
+    pub(crate) fn can_join(&self, team: &str, size: u32) -> bool {
+        self.base(team).is_some() || !self.free_centers(size).is_empty()
+    }
+
+    /// 陣地を置ける中心。ほかの陣地と重ならず、旗も置かれていないマス
+    fn free_centers(&self, size: u32) -> Vec<Position> {
+        let far = size as i32 - 2;
+        (1..=far)
+            .flat_map(|y| (1..=far).map(move |x| Position::new(x, y)))
+            .filter(|p| {
+                self.bases
+                    .iter()
+                    .all(|b| (p.x - b.center.x).abs() > 2 || (p.y - b.center.y).abs() > 2)
+                    && self.flags.iter().all(|f| f.position != *p)
+            })
+            .collect()
+    }
+
+    /// チームの陣地と旗を用意する。陣地は四隅から順に置き、5 チーム目からはほかの陣地と重ならない
+    /// ランダムな位置にする。置く場所がなければ何もしない (`can_join` で先に確かめる)
+    pub(crate) fn join(&mut self, team: &str, size: u32, rng: &mut impl Rng) {
+        if self.base(team).is_some() {
+            return;
+        }
+        let far = size as i32 - 2;
+        let corners = [(1, 1), (far, far), (far, 1), (1, far)];
+        let free = self.free_centers(size);
+        let corner = corners
+            .get(self.bases.len())
+            .map(|&(x, y)| Position::new(x, y))
+            .filter(|p| free.contains(p));
+        let Some(center) = corner.or_else(|| free.choose(rng).copied()) else {
+            return;
+        };
+        self.bases.push(Base {
+            team: team.to_string(),
+            center,
+            captures: 0,
+        });
+        self.flags.push(Flag {
+            team: team.to_string(),
+            position: center,
+            carried_by: None,
+            carrier: None,
+        });
+    }
+
+    /// カニが `crab.position` に歩いてきたときの処理。相手の旗の上に来たら拾い、
+    /// 旗を持っているカニが動いたら旗も一緒に動かす。状態が変わったら true
+    pub(crate) fn walked(&mut self, crab: &Crab) -> bool {
+        let Some(team) = &crab.team else {
+            return false;
+        };
+        let mut changed = false;
+        for flag in &mut self.flags {
+            if flag.carrier == Some(crab.token) {
+                flag.position = crab.position;
+                changed = true;
+            } else if flag.carrier.is_none() && flag.position == crab.position && flag.team != *team
+            {
+                flag.carrier = Some(crab.token);
+                flag.carried_by = Some(crab.name.clone());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// game_cycle ごとの処理。持ち帰られた旗を得点にして陣地に戻し、
+    /// 持っていたカニがいなくなった旗も陣地に戻す。得点したカニのトークンを返す
+    pub(crate) fn tick(&mut self, crabs: &[Crab]) -> (Vec<Token>, bool) {
+        let mut scorers = vec![];
+        let mut changed = false;
+        for i in 0..self.flags.len() {
+            let Some(token) = self.flags[i].carrier else {
+                continue;
+            };
+            let carrier = crabs.iter().find(|c| c.token == token);
+            let captured = carrier.and_then(|carrier| {
+                let team = carrier.team.as_deref()?;
+                // 自分の旗が陣地にある (誰にも持たれていない) ときだけ得点になる
+                let own_flag_home = self
+                    .flags
+                    .iter()
+                    .any(|f| f.team == team && f.carrier.is_none());
+                let base = self.bases.iter().position(|b| b.team == team)?;
+                (own_flag_home && self.bases[base].contains(&carrier.position)).then_some(base)
+            });
+            match (carrier, captured) {
+                (Some(carrier), Some(base)) => {
+                    self.bases[base].captures += 1;
+                    scorers.push(carrier.token);
+                    self.return_home(i);
+                    changed = true;
+                }
+                (Some(carrier), None) => {
+                    // 押し出されたカニにも旗がついていく
+                    changed |= self.flags[i].position != carrier.position;
+                    self.flags[i].position = carrier.position;
+                }
+                (None, _) => {
+                    self.return_home(i);
+                    changed = true;
+                }
+            }
+        }
+        (scorers, changed)
+    }
+
+    fn return_home(&mut self, index: usize) {
+        let home = self.base(&self.flags[index].team).map(|b| b.center);
+        let flag = &mut self.flags[index];
+        flag.carrier = None;
+        flag.carried_by = None;
+        if let Some(home) = home {
+            flag.position = home;
+        }
+    }
+
+    /// `position` のマスに置かれている旗のチーム
+    pub(crate) fn flag_at(&self, position: &Position) -> Option<&str> {
+        self.flags
+            .iter()
+            .find(|f| f.carrier.is_none() && f.position == *position)
+            .map(|f| f.team.as_str())
+    }
+
+    /// `position` のマスを含む陣地のチーム。`from` (Scan したカニの位置) を含む陣地は除く
+    pub(crate) fn base_at(&self, position: &Position, from: &Position) -> Option<&str> {
+        self.bases
+            .iter()
+            .find(|b| b.contains(position) && !b.contains(from))
+            .map(|b| b.team.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bases_do_not_overlap() {
+        let mut ctf = CaptureTheFlag::default();
+        let mut rng = rand::thread_rng();
+        for team in ["a", "b", "c", "d", "e", "f"] {
+            assert!(ctf.can_join(team, 10));
+            ctf.join(team, 10, &mut rng);
+        }
+        // 四隅の後は、ほかの陣地と重ならない場所に置く
+        assert_eq!(ctf.bases[3].center, Position::new(1, 8));
+        for (i, a) in ctf.bases.iter().enumerate() {
+            for b in &ctf.bases[i + 1..] {
+                assert!((a.center.x - b.center.x).abs() > 2 || (a.center.y - b.center.y).abs() > 2);
+            }
+        }
+        // 置く場所がなくなれば入れない。小さすぎるフィールドにも置けない
+        let mut small = CaptureTheFlag::default();
+        for team in ["a", "b", "c", "d"] {
+            small.join(team, 7, &mut rng);
+        }
+        assert!(!small.can_join("e", 7));
+        assert!(small.can_join("a", 7));
+        assert!(!CaptureTheFlag::default().can_join("a", 2));
+    }
+}
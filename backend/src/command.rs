@@ -1,32 +1,24 @@
-pub(crate) mod game_cycle_command;
-pub(crate) mod player_command;
+pub mod game_cycle_command;
+pub use kani_life_protocol::player_command;
+pub use kani_life_protocol::CommandResult;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub(crate) enum Command {
+pub enum Command {
     PlayerCommand(player_command::PlayerCommand),
     GameCycleCommand(game_cycle_command::GameCycleCommand),
 }
 
-use player_command::{PaintResult, ScanResult, SpawnResult, WalkResult};
-#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
-#[serde(tag = "type")]
-pub(crate) enum CommandResult {
-    Ok,
-    Pong,
-    NotImplemented,
-    CrabNotFound,
-    Spawn(SpawnResult),
-    Scan(ScanResult),
-    Turn,
-    Walk(WalkResult),
-    Paint(PaintResult),
-}
+use player_command::{
+    PaintResult, PinchResult, ScanResult, SpawnResult, StageStatusResult, WaitForResult, WalkResult,
+};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub(crate) struct CommandResponse {
-    pub(crate) result: CommandResult,
-    pub(crate) wait: u64,
-    pub(crate) mutated: bool,
+pub struct CommandResponse {
+    pub result: CommandResult,
+    /// 結果を返すまでに待たせる時間 (ms)
+    pub wait: u64,
+    /// ゲームの状態が変わったか
+    pub mutated: bool,
 }
 
 impl CommandResponse {
@@ -63,6 +55,39 @@ impl CommandResponse {
         }
     }
 
+    /// エネルギーが回復するまで `wait` ミリ秒待たせる
+    pub(crate) fn out_of_energy(wait: u64) -> Self {
+        CommandResponse {
+            result: CommandResult::OutOfEnergy,
+            wait,
+            mutated: false,
+        }
+    }
+
+    pub(crate) fn stage_not_found() -> Self {
+        CommandResponse {
+            result: CommandResult::StageNotFound,
+            wait: 0,
+            mutated: false,
+        }
+    }
+
+    pub(crate) fn command_not_allowed() -> Self {
+        CommandResponse {
+            result: CommandResult::CommandNotAllowed,
+            wait: 0,
+            mutated: false,
+        }
+    }
+
+    pub(crate) fn sandbox_not_found() -> Self {
+        CommandResponse {
+            result: CommandResult::SandboxNotFound,
+            wait: 0,
+            mutated: false,
+        }
+    }
+
     pub(crate) fn spawn(result: SpawnResult) -> Self {
         CommandResponse {
             result: CommandResult::Spawn(result),
@@ -102,4 +127,28 @@ impl CommandResponse {
             mutated: true,
         }
     }
+
+    pub(crate) fn pinch(result: PinchResult) -> Self {
+        CommandResponse {
+            result: CommandResult::Pinch(result),
+            wait: 300,
+            mutated: true,
+        }
+    }
+
+    pub(crate) fn stage_status(result: StageStatusResult) -> Self {
+        CommandResponse {
+            result: CommandResult::StageStatus(result),
+            wait: 0,
+            mutated: false,
+        }
+    }
+
+    pub(crate) fn wait_for(result: WaitForResult) -> Self {
+        CommandResponse {
+            result: CommandResult::WaitFor(result),
+            wait: 0,
+            mutated: false,
+        }
+    }
 }
@@ -1,4 +1,18 @@
+use crate::command::player_command::SpawnParam;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub(crate) enum GameCycleCommand {
+pub enum GameCycleCommand {
     SpawnFood,
+    /// ゲームモードのルールを進める (得点や旗を戻す処理など)
+    Tick,
+    /// NPC カニを出現させる
+    SpawnNpc(SpawnParam),
+    /// 進行役の合図で、`duration` ミリ秒のあいだごはんを大量発生させる
+    FeedingFrenzy {
+        duration: u64,
+    },
+    /// 進行役の合図で、ペイントバトルの試合をはじめからやり直す
+    StartPaintBattle,
+    /// WaitFor のタイムアウトを確かめるために command_processor を起こす。状態は変えない
+    Wake,
 }
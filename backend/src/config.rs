@@ -0,0 +1,117 @@
+//! 環境変数から読み込むサーバー設定
+
+use crate::npc::Difficulty;
+use crate::telemetry::{LogFormat, TraceExporter};
+use kani_life::food_policy::SpawnPolicy;
+use kani_life::game_config::{
+    CrabConfig, EnergyConfig, FoodConfig, FoodWeights, GameConfig, GameMode, GrowthConfig,
+    PinchEffect, PoisonEffect, TeamConfig, TeamScoring,
+};
+use std::str::FromStr;
+
+/// NPC カニの設定
+#[derive(Debug, Clone)]
+pub(crate) struct NpcConfig {
+    /// 出現させる NPC の数
+    pub count: usize,
+    /// NPC の強さ
+    pub difficulty: Difficulty,
+}
+
+impl NpcConfig {
+    /// `KANI_NPC_COUNT` と `KANI_NPC_DIFFICULTY` から読み込む
+    pub(crate) fn from_env() -> Self {
+        NpcConfig {
+            count: env_or("KANI_NPC_COUNT", 0),
+            difficulty: env_or("KANI_NPC_DIFFICULTY", Difficulty::Normal),
+        }
+    }
+}
+
+/// ゲームのルール
+/// `KANI_FOOD_POLICY` (`random` / `scaled` / `clustered` / `tide` / `away`), `KANI_MAX_FOODS`,
+/// `KANI_FOOD_WEIGHTS` (例: `normal=6,golden=1,poison=2,rotting=1`), `KANI_REVEAL_FOOD_KIND`,
+/// `KANI_POISON_EFFECT` (`points` / `stun`), `KANI_PINCH` (`off` / `steal` / `stun`),
+/// `KANI_SHOVE`, `KANI_ENERGY`, `KANI_GROWTH` から読み込む
+pub(crate) fn game_config_from_env() -> GameConfig {
+    GameConfig {
+        mode: env_or("KANI_MODE", GameMode::Classic),
+        food: FoodConfig {
+            policy: env_or("KANI_FOOD_POLICY", SpawnPolicy::Random),
+            max_foods: env_or("KANI_MAX_FOODS", 5),
+            weights: env_or("KANI_FOOD_WEIGHTS", FoodWeights::default()),
+            reveal_kind: env_or("KANI_REVEAL_FOOD_KIND", false),
+            poison_effect: env_or("KANI_POISON_EFFECT", PoisonEffect::LosePoints),
+            ..FoodConfig::default()
+        },
+        crab: CrabConfig {
+            pinch: env_or("KANI_PINCH", PinchEffect::Disabled),
+            shove: env_or("KANI_SHOVE", false),
+            ..CrabConfig::default()
+        },
+        energy: EnergyConfig {
+            enabled: env_or("KANI_ENERGY", false),
+            ..EnergyConfig::default()
+        },
+        growth: GrowthConfig {
+            enabled: env_or("KANI_GROWTH", false),
+            ..GrowthConfig::default()
+        },
+        team: TeamConfig {
+            scoring: env_or("KANI_TEAM_SCORING", TeamScoring::Summed),
+        },
+    }
+}
+
+/// 進行役が使うキー (`KANI_ADMIN_KEY`)。未設定なら進行役の操作は使えない
+pub(crate) fn admin_key_from_env() -> Option<String> {
+    std::env::var("KANI_ADMIN_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+/// 霧の中モード (`KANI_FOG_OF_WAR`)。フィールド全体の `state` は進行役のキーを持つ観戦者にしか配信しない
+pub(crate) fn fog_of_war_from_env() -> bool {
+    env_or("KANI_FOG_OF_WAR", false)
+}
+
+/// ログとトレースの設定
+/// ログのレベルは `RUST_LOG` で指定する (デフォルト `info`)
+#[derive(Debug, Clone)]
+pub(crate) struct TelemetryConfig {
+    /// トレースの送信先
+    pub exporter: TraceExporter,
+    /// 記録するトレースの割合 (0.0 ~ 1.0)
+    pub sample_ratio: f64,
+    /// ログの出力形式
+    pub log_format: LogFormat,
+}
+
+impl TelemetryConfig {
+    /// `KANI_TRACE_EXPORTER`, `KANI_TRACE_SAMPLE_RATIO`, `KANI_LOG_FORMAT` から読み込む
+    /// OTLP の送信先は `OTEL_EXPORTER_OTLP_ENDPOINT` などの標準の環境変数で指定する
+    pub(crate) fn from_env() -> Self {
+        TelemetryConfig {
+            exporter: env_or("KANI_TRACE_EXPORTER", TraceExporter::None),
+            sample_ratio: env_or("KANI_TRACE_SAMPLE_RATIO", 1.0),
+            log_format: env_or("KANI_LOG_FORMAT", LogFormat::Pretty),
+        }
+    }
+}
+
+/// 環境変数をパースする。未設定やパースできない場合は default を返す。パースできなければ警告を残す
+fn env_or<T: FromStr>(key: &str, default: T) -> T
+where
+    T::Err: std::fmt::Display,
+{
+    let Ok(value) = std::env::var(key) else {
+        return default;
+    };
+    match value.parse() {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::warn!("Ignored {}={:?}: {}", key, value, e);
+            default
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::command::player_command::SpawnResult;
 use crate::geometry::{Direction, Position, Side};
 use crate::token::Token;
+use rand::Rng;
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -12,13 +13,36 @@ pub(crate) struct Crab {
     pub(crate) point: i32,
     pub(crate) direction: Direction,
     pub(crate) position: Position,
+    /// サーバー内で動く NPC カニか
+    pub(crate) npc: bool,
+    /// はさまれて動けなくなっている間は、この時刻 (ms) までコマンドの結果が返らない
+    #[serde(skip)]
+    pub(crate) stunned_until: u64,
+    /// 残りのエネルギー。エネルギーのルールでないときは None
+    pub(crate) energy: Option<u32>,
+    /// エネルギーの回復を最後に計算した時刻 (ms)
+    #[serde(skip)]
+    pub(crate) energy_at: u64,
+    /// 脱皮した回数。大きいほど体が大きい
+    pub(crate) stage: u32,
+    /// 前回の脱皮から食べたごはんの数
+    #[serde(skip)]
+    pub(crate) molt_progress: u32,
+    /// 所属するチーム名
+    pub(crate) team: Option<String>,
 }
 
 impl Crab {
-    pub(crate) fn spawn(name: &str, hue: f32, game_field_size: u32, keep_out: &[Position]) -> Self {
+    pub(crate) fn spawn(
+        rng: &mut impl Rng,
+        name: &str,
+        hue: f32,
+        game_field_size: u32,
+        keep_out: &[Position],
+    ) -> Self {
         // keep_out を避けてランダムに Position を生成する
         let position = loop {
-            let pos = Position::random(game_field_size, game_field_size);
+            let pos = Position::random(rng, game_field_size, game_field_size);
             if !keep_out.contains(&pos) {
                 break pos;
             }
@@ -28,8 +52,15 @@ impl Crab {
             token: Token::new(),
             hue,
             point: 0,
-            direction: Direction::random(),
+            direction: Direction::random(rng),
             position,
+            npc: false,
+            stunned_until: 0,
+            energy: None,
+            energy_at: 0,
+            stage: 0,
+            molt_progress: 0,
+            team: None,
         }
     }
 
@@ -48,10 +79,6 @@ impl Crab {
             ..self.clone()
         }
     }
-
-    pub(crate) fn walk_mut(&mut self, side: Side) {
-        self.position = self.position.walk(self.direction, side);
-    }
 }
 
 impl From<Crab> for SpawnResult {
@@ -66,7 +93,7 @@ mod tests {
 
     #[test]
     fn test_spawn() {
-        let crab = Crab::spawn("test", 0.0, 10, &[]);
+        let crab = Crab::spawn(&mut rand::thread_rng(), "test", 0.0, 10, &[]);
         assert_eq!(crab.name, "test");
         assert_eq!(crab.hue, 0.0);
         assert_eq!(crab.point, 0);
@@ -82,6 +109,13 @@ mod tests {
             point: 0,
             direction: Direction::N,
             position: Position::new(0, 0),
+            npc: false,
+            stunned_until: 0,
+            energy: None,
+            energy_at: 0,
+            stage: 0,
+            molt_progress: 0,
+            team: None,
         };
         crab.turn_mut(Side::Right);
         assert_eq!(crab.direction, Direction::E);
@@ -97,6 +131,13 @@ mod tests {
             point: 0,
             direction: Direction::N,
             position: Position::new(0, 0),
+            npc: false,
+            stunned_until: 0,
+            energy: None,
+            energy_at: 0,
+            stage: 0,
+            molt_progress: 0,
+            team: None,
         };
         let crab_moved_right = crab.walk(Side::Right);
         assert_eq!(crab_moved_right.direction, Direction::N);
@@ -0,0 +1,51 @@
+//! ゲームの中で起きたこと
+//!
+//! 状態のスナップショットを見比べなくても、アニメーションや効果音を出せるように、
+//! GameState がコマンドを処理するときに起きたことを記録する。カニはトークンではなく名前で表す
+
+use crate::geometry::Position;
+use crate::stats::WalkBlocked;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum GameEvent {
+    /// ごはんが出た
+    FoodSpawned {
+        position: Position,
+        size: u32,
+    },
+    CrabSpawned {
+        name: String,
+        position: Position,
+    },
+    /// 歩いたか、押し出された
+    CrabMoved {
+        name: String,
+        from: Position,
+        to: Position,
+    },
+    WalkBlocked {
+        name: String,
+        by: WalkBlocked,
+    },
+    FoodEaten {
+        name: String,
+        position: Position,
+        size: u32,
+    },
+    /// 誰も塗っていないか、自分が塗ったマスを塗った
+    CellPainted {
+        name: String,
+        position: Position,
+    },
+    /// ほかのカニが塗ったマスを塗り替えた
+    PaintOverwritten {
+        name: String,
+        position: Position,
+        previous: String,
+    },
+    /// Spawn し直したり、ステージを始めたりしてフィールドからいなくなった
+    CrabRemoved {
+        name: String,
+    },
+}
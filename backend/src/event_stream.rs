@@ -0,0 +1,68 @@
+//! ゲームの中で起きたことを Server-Sent Events (`GET /api/events`) で配信する
+//!
+//! socket.io を使わないクライアント向け。霧の中モードでは `?key=<KANI_ADMIN_KEY>` を付けたときだけ購読できる
+
+use crate::admin::SocketAuth;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use kani_life::event::GameEvent;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// 購読者ごとにためておくイベントの数。これより遅れた購読者は古いイベントを読み飛ばす
+const CAPACITY: usize = 256;
+
+#[derive(Clone)]
+struct EventStreamState {
+    events: broadcast::Sender<GameEvent>,
+    admin_key: Option<String>,
+    fog_of_war: bool,
+}
+
+/// command_processor からイベントを送るためのセンダー
+pub(crate) fn channel() -> broadcast::Sender<GameEvent> {
+    broadcast::channel(CAPACITY).0
+}
+
+pub(crate) fn router(
+    events: broadcast::Sender<GameEvent>,
+    admin_key: Option<String>,
+    fog_of_war: bool,
+) -> Router {
+    Router::new()
+        .route("/api/events", get(get_events))
+        .with_state(EventStreamState {
+            events,
+            admin_key,
+            fog_of_war,
+        })
+}
+
+/// 1 つのイベントを 1 つの `data` (JSON) として送る
+async fn get_events(
+    State(state): State<EventStreamState>,
+    Query(auth): Query<SocketAuth>,
+) -> Response {
+    if !auth.can_see_field(&state.admin_key, state.fog_of_war) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let rx = state.events.subscribe();
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((Event::default().json_data(event), rx)),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event stream lagged: {} events skipped", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
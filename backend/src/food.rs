@@ -1,3 +1,4 @@
+use crate::command::player_command::FoodKind;
 use crate::geometry::Position;
 use crate::token::Token;
 use rand::Rng;
@@ -8,22 +9,89 @@ pub(crate) struct Food {
     pub id: Token,
     pub position: Position,
     pub size: u32,
+    pub kind: FoodKind,
+    /// 腐りかけのごはんが次に小さくなる時刻 (ms)
+    #[serde(skip)]
+    pub rot_at: u64,
+}
+
+/// 観戦画面に送るごはん。種類を明かさないルールでは `kind` を送らない
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PublicFood<'a> {
+    id: &'a Token,
+    position: &'a Position,
+    size: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<FoodKind>,
 }
 
 impl Food {
-    pub(crate) fn spawn(max_size: u32, game_field_size: u32, keep_out: &[Position]) -> Self {
-        // keep_out を避けてランダムに Position を生成する
-        let position = loop {
-            let pos = Position::random(game_field_size, game_field_size);
-            if !keep_out.contains(&pos) {
-                break pos;
-            }
-        };
+    pub(crate) fn public(&self, reveal_kind: bool) -> PublicFood<'_> {
+        PublicFood {
+            id: &self.id,
+            position: &self.position,
+            size: self.size,
+            kind: reveal_kind.then_some(self.kind),
+        }
+    }
+
+    /// 置く位置は出現ポリシーが決める
+    pub(crate) fn spawn(
+        rng: &mut impl Rng,
+        max_size: u32,
+        position: Position,
+        kind: FoodKind,
+    ) -> Self {
         Self {
             id: Token::new(),
             position,
             // random で生成されるので 1 以上
-            size: rand::thread_rng().gen_range(1..=max_size),
+            size: rng.gen_range(1..=max_size),
+            kind,
+            rot_at: 0,
+        }
+    }
+
+    /// 食べたときに増えるポイント。毒のごはんはマイナスになる
+    pub(crate) fn point(&self) -> i32 {
+        match self.kind {
+            FoodKind::Normal | FoodKind::Rotting => self.size as i32,
+            FoodKind::Golden => self.size as i32 * 3,
+            FoodKind::Poison => -(self.size as i32),
         }
     }
+
+    /// 腐りかけのごはんを `now` の時点まで小さくする。消えるときは false を返す
+    pub(crate) fn rot(&mut self, now: u64, interval: u64) -> bool {
+        if self.kind != FoodKind::Rotting {
+            return true;
+        }
+        while self.rot_at <= now && self.size > 0 {
+            self.size -= 1;
+            self.rot_at += interval;
+        }
+        self.size > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotting_food_shrinks_and_disappears() {
+        let mut food = Food {
+            id: Token::new(),
+            position: Position::new(0, 0),
+            size: 2,
+            kind: FoodKind::Rotting,
+            rot_at: 1000,
+        };
+        assert!(food.rot(999, 1000));
+        assert_eq!(food.size, 2);
+        assert!(food.rot(1000, 1000));
+        assert_eq!(food.size, 1);
+        assert!(!food.rot(2500, 1000));
+    }
 }
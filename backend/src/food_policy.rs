@@ -0,0 +1,206 @@
+//! ごはんの出し方 (出現ポリシー)
+//!
+//! game_cycle が SpawnFood を送るたびに、設定されたポリシーがどこにいくつごはんを置くか、
+//! 次の SpawnFood までどれだけ待つかを決める
+
+use crate::geometry::Position;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::str::FromStr;
+
+/// ポリシーがごはんの位置を決めるときに見るフィールドの様子
+pub(crate) struct FieldView<'a> {
+    pub size: u32,
+    /// ゲーム内の時刻 (ms)
+    pub now: u64,
+    pub crabs: &'a [Position],
+    pub foods: &'a [Position],
+}
+
+impl FieldView<'_> {
+    /// カニもごはんもないマス
+    fn free_cells(&self) -> Vec<Position> {
+        let size = self.size as i32;
+        (0..size)
+            .flat_map(|y| (0..size).map(move |x| Position::new(x, y)))
+            .filter(|p| !self.crabs.contains(p) && !self.foods.contains(p))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum SpawnPolicy {
+    /// ランダムな位置に 1 つずつ置く
+    #[default]
+    Random,
+    /// カニが多いほどたくさん置けるようにする
+    ScaledByCrabs {
+        /// カニ 1 匹あたりに増やす上限
+        per_crab: f32,
+    },
+    /// ランダムな場所のまわりにまとめて置く
+    Clustered {
+        /// 1 回に置く数
+        patch_size: usize,
+    },
+    /// 左右に行き来する潮の満ち引きの線に沿って置く
+    TideLine {
+        /// 潮の線が 1 列動くまでの時間 (ms)
+        period: u64,
+    },
+    /// どのカニからもなるべく離れた位置に置く。`Random` と同じく 1 回に 1 個ずつ置く
+    AwayFromCrabs {
+        /// これ以上離れていればよいとするマンハッタン距離
+        min_distance: i32,
+    },
+    /// 進行役が起こす大量発生。上限を超えて短い間隔でどんどん置く
+    FeedingFrenzy {
+        /// 1 回に置く数
+        burst: usize,
+    },
+}
+
+impl SpawnPolicy {
+    /// フィールドに置いておけるごはんの上限
+    pub(crate) fn cap(&self, max_foods: usize, crabs: usize) -> usize {
+        match self {
+            SpawnPolicy::ScaledByCrabs { per_crab } => {
+                max_foods.max((crabs as f32 * per_crab).ceil() as usize)
+            }
+            SpawnPolicy::FeedingFrenzy { .. } => max_foods * 4,
+            _ => max_foods,
+        }
+    }
+
+    /// 次の SpawnFood までの待ち時間 (ms)
+    pub(crate) fn interval(&self) -> u64 {
+        match self {
+            SpawnPolicy::TideLine { period } => *period,
+            SpawnPolicy::FeedingFrenzy { .. } => 500,
+            _ => 5000,
+        }
+    }
+
+    /// ごはんを置く位置。`room` 個より多くは返さない
+    pub(crate) fn positions(
+        &self,
+        field: &FieldView,
+        room: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<Position> {
+        let free = field.free_cells();
+        let count = match self {
+            SpawnPolicy::Clustered { patch_size } => *patch_size,
+            SpawnPolicy::FeedingFrenzy { burst } => *burst,
+            _ => 1,
+        }
+        .min(room);
+        match self {
+            SpawnPolicy::Random
+            | SpawnPolicy::ScaledByCrabs { .. }
+            | SpawnPolicy::FeedingFrenzy { .. } => {
+                free.choose_multiple(rng, count).copied().collect()
+            }
+            SpawnPolicy::Clustered { .. } => {
+                let Some(center) = free.choose(rng).copied() else {
+                    return vec![];
+                };
+                // 中心から近い順に埋める
+                let mut near: Vec<Position> = free
+                    .into_iter()
+                    .filter(|p| distance(p, &center) <= 2)
+                    .collect();
+                near.shuffle(rng);
+                near.sort_by_key(|p| distance(p, &center));
+                near.truncate(count);
+                near
+            }
+            SpawnPolicy::TideLine { period } => {
+                let x = tide_x(field.size, field.now, *period);
+                let line: Vec<Position> = free.into_iter().filter(|p| p.x == x).collect();
+                line.choose_multiple(rng, count).copied().collect()
+            }
+            SpawnPolicy::AwayFromCrabs { min_distance } => {
+                let far = |p: &Position| {
+                    field
+                        .crabs
+                        .iter()
+                        .map(|c| distance(p, c))
+                        .min()
+                        .unwrap_or(i32::MAX)
+                };
+                let candidates: Vec<Position> = free
+                    .iter()
+                    .filter(|p| far(p) >= *min_distance)
+                    .copied()
+                    .collect();
+                if candidates.is_empty() {
+                    // 十分離れたマスがなければ、いちばん遠いマスにする
+                    free.into_iter().max_by_key(far).into_iter().collect()
+                } else {
+                    candidates.choose_multiple(rng, count).copied().collect()
+                }
+            }
+        }
+    }
+}
+
+/// `random` / `scaled` / `clustered` / `tide` / `away` (パラメーターはデフォルト値)
+impl FromStr for SpawnPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "random" => Ok(SpawnPolicy::Random),
+            "scaled" => Ok(SpawnPolicy::ScaledByCrabs { per_crab: 1.0 }),
+            "clustered" => Ok(SpawnPolicy::Clustered { patch_size: 4 }),
+            "tide" => Ok(SpawnPolicy::TideLine { period: 3000 }),
+            "away" => Ok(SpawnPolicy::AwayFromCrabs { min_distance: 5 }),
+            _ => Err(format!("unknown food spawn policy: {}", s)),
+        }
+    }
+}
+
+fn distance(a: &Position, b: &Position) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+/// `now` の時点で潮の線がある列。左端と右端の間を行き来する
+fn tide_x(size: u32, now: u64, period: u64) -> i32 {
+    let size = size as u64;
+    if size <= 1 {
+        return 0;
+    }
+    let step = now / period.max(1) % (2 * size - 2);
+    if step < size {
+        step as i32
+    } else {
+        (2 * size - 2 - step) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tide_line_goes_back_and_forth() {
+        let xs: Vec<i32> = (0..8).map(|i| tide_x(4, i * 1000, 1000)).collect();
+        assert_eq!(xs, vec![0, 1, 2, 3, 2, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_away_from_crabs() {
+        let crabs = [Position::new(0, 0)];
+        let field = FieldView {
+            size: 5,
+            now: 0,
+            crabs: &crabs,
+            foods: &[],
+        };
+        // 5x5 では距離 9 以上のマスはないので、いちばん遠いマスになる
+        let policy = SpawnPolicy::AwayFromCrabs { min_distance: 9 };
+        let positions = policy.positions(&field, 1, &mut rand::thread_rng());
+        assert_eq!(positions, vec![Position::new(4, 4)]);
+    }
+}
@@ -0,0 +1,366 @@
+//! ルーム (GameState) ごとのゲームのルール設定
+
+use crate::command::player_command::{FoodKind, PlayerCommand};
+use crate::food_policy::SpawnPolicy;
+use rand::Rng;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameConfig {
+    pub mode: GameMode,
+    pub food: FoodConfig,
+    pub crab: CrabConfig,
+    pub energy: EnergyConfig,
+    pub growth: GrowthConfig,
+    pub team: TeamConfig,
+}
+
+/// ゲームモード。クラシックルールに加えて、モードごとのルールが game_cycle の Tick で進む
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum GameMode {
+    /// ごはんを食べてマスを塗るだけのルール
+    #[default]
+    Classic,
+    /// チームごとの陣地と旗を取り合う
+    CaptureTheFlag {
+        /// 相手の旗を持ち帰ったカニがもらえるポイント
+        capture_point: i32,
+    },
+    /// 移動する丘を取り合う
+    KingOfTheHill {
+        /// 丘の数
+        zones: usize,
+        /// 丘が動くまでの時間 (ms)
+        move_interval: u64,
+        /// Tick ごとに丘にいるカニがもらえるポイント
+        point_per_tick: i32,
+    },
+    /// 試合時間のあいだ塗り合い、最後に塗ったマスを数える
+    PaintBattle {
+        /// 試合時間 (ms)
+        match_time: u64,
+        /// 相手が塗ったマスを塗り替えるのに使うポイント (何も塗られていないマスは 1)
+        overwrite_cost: i32,
+    },
+}
+
+impl GameMode {
+    /// 次の Tick までの待ち時間 (ms)
+    pub(crate) fn tick_interval(&self) -> u64 {
+        match self {
+            GameMode::Classic => 1000,
+            GameMode::CaptureTheFlag { .. } => 500,
+            GameMode::KingOfTheHill { .. } => 1000,
+            GameMode::PaintBattle { .. } => 1000,
+        }
+    }
+}
+
+/// `classic` / `ctf` / `hill` / `paint-battle` (パラメーターはデフォルト値)
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "classic" => Ok(GameMode::Classic),
+            "ctf" => Ok(GameMode::CaptureTheFlag { capture_point: 10 }),
+            "hill" => Ok(GameMode::KingOfTheHill {
+                zones: 1,
+                move_interval: 15_000,
+                point_per_tick: 1,
+            }),
+            "paint-battle" => Ok(GameMode::PaintBattle {
+                match_time: 180_000,
+                overwrite_cost: 3,
+            }),
+            _ => Err(format!("unknown game mode: {}", s)),
+        }
+    }
+}
+
+/// チーム戦のルール
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TeamConfig {
+    /// チームのポイントの数え方
+    pub scoring: TeamScoring,
+}
+
+/// チームのポイントの数え方
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TeamScoring {
+    /// カニごとにポイントを持ち、チームのポイントはその合計
+    #[default]
+    Summed,
+    /// チームでひとつのポイントを共有する。誰が食べても誰が塗っても同じポイントが増減する
+    Pooled,
+}
+
+impl FromStr for TeamScoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sum" => Ok(TeamScoring::Summed),
+            "pool" => Ok(TeamScoring::Pooled),
+            _ => Err(format!("unknown team scoring: {}", s)),
+        }
+    }
+}
+
+/// 脱皮による成長のルール
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrowthConfig {
+    /// 成長するか
+    pub enabled: bool,
+    /// 脱皮するまでに食べるごはんの数
+    pub foods_per_stage: u32,
+    /// 最大の段階 (出現したときは 0)
+    pub max_stage: u32,
+    /// 1 段階ごとに増える Walk の待ち時間 (ms)
+    pub walk_slowdown: u64,
+    /// この段階から 3x3 のマスを一度に塗れる
+    pub wide_paint_stage: u32,
+}
+
+impl Default for GrowthConfig {
+    fn default() -> Self {
+        GrowthConfig {
+            enabled: false,
+            foods_per_stage: 3,
+            max_stage: 3,
+            walk_slowdown: 200,
+            wide_paint_stage: 2,
+        }
+    }
+}
+
+/// カニのエネルギー (スタミナ) のルール。コマンドを連打するだけでは勝てないようにする
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnergyConfig {
+    /// エネルギーのルールを使うか
+    pub enabled: bool,
+    /// エネルギーの上限。出現したときは満タン
+    pub max: u32,
+    /// 1 秒ごとに回復する量
+    pub regen_per_sec: u32,
+    /// ごはんのサイズ 1 あたりに回復する量
+    pub per_food_size: u32,
+    /// コマンドごとに使う量
+    pub walk: u32,
+    pub paint: u32,
+    pub scan: u32,
+    pub turn: u32,
+    pub pinch: u32,
+}
+
+impl Default for EnergyConfig {
+    fn default() -> Self {
+        EnergyConfig {
+            enabled: false,
+            max: 100,
+            regen_per_sec: 2,
+            per_food_size: 10,
+            walk: 4,
+            paint: 3,
+            scan: 1,
+            turn: 1,
+            pinch: 5,
+        }
+    }
+}
+
+impl EnergyConfig {
+    /// コマンドで使うエネルギー
+    pub(crate) fn cost(&self, command: &PlayerCommand) -> u32 {
+        match command {
+            PlayerCommand::Ping
+            | PlayerCommand::Spawn(_)
+            | PlayerCommand::StartStage(_)
+            | PlayerCommand::StageStatus(_)
+            | PlayerCommand::ResetSandbox(_)
+            | PlayerCommand::Graduate(_)
+            | PlayerCommand::WaitFor(_) => 0,
+            PlayerCommand::Scan(_) => self.scan,
+            PlayerCommand::Turn(_) => self.turn,
+            PlayerCommand::Walk(_) => self.walk,
+            PlayerCommand::Paint(_) => self.paint,
+            PlayerCommand::Pinch(_) => self.pinch,
+        }
+    }
+}
+
+/// カニどうしのやりとりのルール。デフォルトは初心者向けの手出しできないルール
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrabConfig {
+    /// Pinch で正面のカニをはさんだときの効果
+    pub pinch: PinchEffect,
+    /// Walk でぶつかったカニを、その先のマスが空いていれば押し出せるか
+    pub shove: bool,
+    /// はさまれて動けなくなる時間 (ms)
+    pub stun_wait: u64,
+}
+
+impl Default for CrabConfig {
+    fn default() -> Self {
+        CrabConfig {
+            pinch: PinchEffect::Disabled,
+            shove: false,
+            stun_wait: 2000,
+        }
+    }
+}
+
+/// Pinch で正面のカニをはさんだときの効果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinchEffect {
+    /// はさめない (Pinch は常に失敗する)
+    Disabled,
+    /// ごはんポイントを 1 奪う
+    Steal,
+    /// しばらく動けなくする (はさまれたカニのコマンドの待ち時間が長くなる)
+    Stun,
+}
+
+impl FromStr for PinchEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(PinchEffect::Disabled),
+            "steal" => Ok(PinchEffect::Steal),
+            "stun" => Ok(PinchEffect::Stun),
+            _ => Err(format!("unknown pinch effect: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoodConfig {
+    /// どこにいくつごはんを置くか
+    pub policy: SpawnPolicy,
+    /// フィールドに置いておけるごはんの上限 (ポリシーによって増える)
+    pub max_foods: usize,
+    /// ごはんの種類ごとの出現しやすさ
+    pub weights: FoodWeights,
+    /// Scan でごはんの種類を明かすか (上級者向けルール)
+    pub reveal_kind: bool,
+    /// 毒のごはんを食べたときの効果
+    pub poison_effect: PoisonEffect,
+    /// 毒のごはんを食べて動けなくなる時間 (ms)
+    pub stun_wait: u64,
+    /// 腐りかけのごはんが 1 小さくなるまでの時間 (ms)
+    pub rot_interval: u64,
+    /// 大量発生のときのポリシー
+    pub frenzy: SpawnPolicy,
+}
+
+impl Default for FoodConfig {
+    fn default() -> Self {
+        FoodConfig {
+            policy: SpawnPolicy::Random,
+            max_foods: 5,
+            weights: FoodWeights::default(),
+            reveal_kind: false,
+            poison_effect: PoisonEffect::LosePoints,
+            stun_wait: 3000,
+            rot_interval: 10_000,
+            frenzy: SpawnPolicy::FeedingFrenzy { burst: 3 },
+        }
+    }
+}
+
+/// ごはんの種類ごとの出現の重み。デフォルトは普通のごはんだけ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoodWeights {
+    pub normal: u32,
+    pub golden: u32,
+    pub poison: u32,
+    pub rotting: u32,
+}
+
+impl Default for FoodWeights {
+    fn default() -> Self {
+        FoodWeights {
+            normal: 1,
+            golden: 0,
+            poison: 0,
+            rotting: 0,
+        }
+    }
+}
+
+impl FoodWeights {
+    /// 重みに従ってごはんの種類を選ぶ。重みがすべて 0 のときは普通のごはん
+    pub(crate) fn choose(&self, rng: &mut impl Rng) -> FoodKind {
+        let table = [
+            (FoodKind::Normal, self.normal),
+            (FoodKind::Golden, self.golden),
+            (FoodKind::Poison, self.poison),
+            (FoodKind::Rotting, self.rotting),
+        ];
+        let total: u32 = table.iter().map(|(_, w)| w).sum();
+        if total == 0 {
+            return FoodKind::Normal;
+        }
+        let mut n = rng.gen_range(0..total);
+        for (kind, weight) in table {
+            if n < weight {
+                return kind;
+            }
+            n -= weight;
+        }
+        unreachable!()
+    }
+}
+
+/// `normal=6,golden=1,poison=2,rotting=1` の形式。書かなかった種類は 0 になる
+impl FromStr for FoodWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = FoodWeights {
+            normal: 0,
+            golden: 0,
+            poison: 0,
+            rotting: 0,
+        };
+        for pair in s.split(',').filter(|p| !p.trim().is_empty()) {
+            let (kind, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("invalid food weight: {}", pair))?;
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid food weight: {}", pair))?;
+            match kind.trim().to_ascii_lowercase().as_str() {
+                "normal" => weights.normal = weight,
+                "golden" => weights.golden = weight,
+                "poison" => weights.poison = weight,
+                "rotting" => weights.rotting = weight,
+                _ => return Err(format!("unknown food kind: {}", kind)),
+            }
+        }
+        Ok(weights)
+    }
+}
+
+/// 毒のごはんを食べたときの効果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoisonEffect {
+    /// サイズ分のポイントを失う (0 より下にはならない)
+    LosePoints,
+    /// しばらく動けなくなる (Walk の待ち時間が長くなる)
+    Stun,
+}
+
+impl FromStr for PoisonEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "points" => Ok(PoisonEffect::LosePoints),
+            "stun" => Ok(PoisonEffect::Stun),
+            _ => Err(format!("unknown poison effect: {}", s)),
+        }
+    }
+}
@@ -1,91 +1,800 @@
+use crate::camera::{Camera, View, Viewport};
+use crate::capture_the_flag::CaptureTheFlag;
 use crate::command::game_cycle_command::GameCycleCommand;
 use crate::command::player_command::{
-    PaintParam, PaintResult, PlayerCommand, ScanParam, ScanResult, SpawnParam, TurnParam,
-    WalkParam, WalkResult, WhatYouCanSee,
+    FoodKind, PaintParam, PaintResult, PinchParam, PinchResult, PlayerCommand, ScanParam,
+    ScanResult, SpawnParam, SpawnResult, StageStatusParam, StageStatusResult, StartStageParam,
+    TurnParam, WaitForParam, WaitForResult, WaitReason, WalkParam, WalkResult, WhatYouCanSee,
 };
-use crate::command::{Command, CommandResponse};
+use crate::command::{Command, CommandResponse, CommandResult};
 use crate::crab::Crab;
-use crate::food::Food;
-use crate::geometry::Position;
+use crate::event::GameEvent;
+use crate::food::{Food, PublicFood};
+use crate::food_policy::FieldView;
+use crate::game_config::{GameConfig, GameMode, PinchEffect, PoisonEffect, TeamScoring};
+use crate::geometry::{Direction, Position};
+use crate::king_of_the_hill::KingOfTheHill;
 use crate::paint::Paint;
+use crate::paint_battle::{self, PaintBattle, PaintTally};
+use crate::perception::Perception;
+use crate::sandbox::Sandbox;
+use crate::stage::{self, Stage, StagePlay};
+use crate::stats::{CrabStats, WalkBlocked};
+use crate::team::{self, TeamScore};
 use crate::token::Token;
+use crate::wait_for::Watch;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, PartialEq, serde::Serialize)]
-#[serde(rename_all = "camelCase")]
-pub(crate) struct GameState {
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameState {
     size: u32,
     crabs: Vec<Crab>,
     foods: Vec<Food>,
     paints: Vec<Paint>,
+    /// 通れない岩。パズルのステージでだけ置く
+    rocks: Vec<Position>,
+    /// キャプチャー・ザ・フラッグモードの陣地と旗
+    capture_the_flag: Option<CaptureTheFlag>,
+    /// キング・オブ・ザ・ヒルモードの丘
+    king_of_the_hill: Option<KingOfTheHill>,
+    /// ペイントバトルモードの試合
+    paint_battle: Option<PaintBattle>,
+    /// カニやごはんの出現位置を決める乱数
+    rng: StdRng,
+    /// ゲーム内の時刻 (ms)。サーバーでは起動からの経過時間、シミュレーターでは仮想時刻
+    now: u64,
+    /// カニ名ごとの行動の統計。除去されたカニの分も残す
+    stats: BTreeMap<String, CrabStats>,
+    config: GameConfig,
+    /// 大量発生が終わる時刻 (ms)
+    frenzy_until: u64,
+    /// チーム名ごとの色相。最初にチームに入ったカニの色相になる
+    teams: BTreeMap<String, f32>,
+    /// フィールドを離れてパズルのステージを遊んでいるカニ
+    stage_plays: Vec<StagePlay>,
+    /// 自分だけの練習用サンドボックスにいるカニ
+    sandboxes: Vec<Sandbox>,
+    /// 直前にポイントを取ったカニ名。観戦のハイライトで追いかける
+    highlight: Option<String>,
+    /// 直前のコマンドを処理したときに起きたこと
+    events: Vec<GameEvent>,
+}
+
+/// 観戦画面に送る GameState。カメラの `view` では映る範囲のものだけを借りる
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PublicState<'a> {
+    size: u32,
+    crabs: Vec<&'a Crab>,
+    foods: Vec<PublicFood<'a>>,
+    paints: Vec<&'a Paint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capture_the_flag: &'a Option<CaptureTheFlag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    king_of_the_hill: &'a Option<KingOfTheHill>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paint_battle: &'a Option<PaintBattle>,
+}
+
+impl serde::Serialize for GameState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.public(|_| true).serialize(serializer)
+    }
 }
 
 impl GameState {
-    pub(crate) fn new(size: u32) -> GameState {
+    pub fn new(size: u32) -> GameState {
+        Self::with_rng(size, StdRng::from_entropy())
+    }
+
+    /// 乱数のシードを固定する。同じコマンド列を与えると同じ結果になる
+    pub fn with_seed(size: u32, seed: u64) -> GameState {
+        Self::with_rng(size, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(size: u32, rng: StdRng) -> GameState {
         GameState {
             size,
             crabs: vec![],
             foods: vec![],
             paints: vec![],
+            rocks: vec![],
+            capture_the_flag: None,
+            king_of_the_hill: None,
+            paint_battle: None,
+            rng,
+            now: 0,
+            stats: BTreeMap::new(),
+            config: GameConfig::default(),
+            frenzy_until: 0,
+            teams: BTreeMap::new(),
+            stage_plays: vec![],
+            sandboxes: vec![],
+            highlight: None,
+            events: vec![],
+        }
+    }
+
+    /// 練習用のサンドボックスの GameState。モードはクラシックにして、`token` のカニだけを出現させる
+    pub(crate) fn for_sandbox(
+        size: u32,
+        config: GameConfig,
+        token: Token,
+        name: &str,
+        hue: f32,
+        seed: u64,
+    ) -> GameState {
+        let mut state = Self::with_seed(size, seed).with_config(GameConfig {
+            mode: GameMode::Classic,
+            ..config
+        });
+        state.spawn_with_token(&SpawnParam::new(name, hue), false, token);
+        state
+    }
+
+    /// パズルのステージを遊ぶための GameState。ステージを始めたカニだけがいる
+    pub(crate) fn for_stage(
+        stage: &Stage,
+        token: Token,
+        name: &str,
+        hue: f32,
+        seed: u64,
+    ) -> GameState {
+        let mut state = Self::with_seed(stage.size, seed);
+        state.rocks = stage.rocks.clone();
+        let mut crab = Crab::spawn(&mut state.rng, name, hue, stage.size, &[]);
+        crab.token = token;
+        crab.position = stage.start;
+        crab.direction = stage.direction;
+        crab.point = stage.point;
+        state.crabs.push(crab);
+        for position in &stage.foods {
+            let food = Food::spawn(&mut state.rng, 1, *position, FoodKind::Normal);
+            state.foods.push(food);
+        }
+        for _ in 0..stage.random_foods {
+            let position = loop {
+                let pos = Position::random(&mut state.rng, stage.size, stage.size);
+                let taken = pos == stage.start
+                    || state.rocks.contains(&pos)
+                    || state.foods.iter().any(|f| f.position == pos);
+                if !taken {
+                    break pos;
+                }
+            };
+            let food = Food::spawn(&mut state.rng, 1, position, FoodKind::Normal);
+            state.foods.push(food);
+        }
+        state
+    }
+
+    /// ルールを設定する
+    pub fn with_config(mut self, config: GameConfig) -> GameState {
+        match config.mode {
+            GameMode::Classic => {}
+            GameMode::CaptureTheFlag { .. } => {
+                self.capture_the_flag = Some(CaptureTheFlag::default());
+            }
+            GameMode::KingOfTheHill { .. } => {
+                self.king_of_the_hill = Some(KingOfTheHill::default());
+            }
+            GameMode::PaintBattle { .. } => {
+                self.paint_battle = Some(PaintBattle::default());
+            }
+        }
+        self.config = config;
+        self
+    }
+
+    /// ゲーム内の時刻を進める。コマンドを処理する前に呼ぶ
+    pub fn set_now(&mut self, now: u64) {
+        self.now = now;
+    }
+
+    pub fn crab_count(&self) -> usize {
+        self.crabs.len()
+    }
+
+    pub fn food_count(&self) -> usize {
+        self.foods.len()
+    }
+
+    pub fn paint_count(&self) -> usize {
+        self.paints.len()
+    }
+
+    pub(crate) fn crabs(&self) -> &[Crab] {
+        &self.crabs
+    }
+
+    pub(crate) fn paints(&self) -> &[Paint] {
+        &self.paints
+    }
+
+    /// カメラに映るフィールド。追いかけるカニがいなければ None
+    pub fn view(&self, camera: &Camera, radius: u32) -> Option<View<'_>> {
+        let (crab, viewport) = self.viewport(camera, radius)?;
+        Some(View {
+            following: &crab.name,
+            viewport,
+            state: self.public(|position| viewport.contains(position)),
+        })
+    }
+
+    /// 観戦画面に送る形で、`visible` な位置にあるカニとごはん、ペイントを借りる
+    fn public(&self, visible: impl Fn(&Position) -> bool) -> PublicState<'_> {
+        // ごはんの種類は Scan でしかわからないルールなら、観戦画面にも種類を出さない
+        let reveal_kind = self.config.food.reveal_kind;
+        PublicState {
+            size: self.size,
+            crabs: self.crabs.iter().filter(|c| visible(&c.position)).collect(),
+            foods: self
+                .foods
+                .iter()
+                .filter(|f| visible(&f.position))
+                .map(|f| f.public(reveal_kind))
+                .collect(),
+            paints: self
+                .paints
+                .iter()
+                .filter(|p| visible(&p.position))
+                .collect(),
+            capture_the_flag: &self.capture_the_flag,
+            king_of_the_hill: &self.king_of_the_hill,
+            paint_battle: &self.paint_battle,
+        }
+    }
+
+    /// 直前のコマンドを処理したときに起きたことのうち、カメラに映る範囲で起きたこと
+    pub fn view_events(&self, camera: &Camera, radius: u32) -> Vec<GameEvent> {
+        let Some((_, viewport)) = self.viewport(camera, radius) else {
+            return vec![];
+        };
+        let visible = |position: &Position| viewport.contains(position);
+        // 位置を持たないイベントは、そのカニが映っているかで決める
+        let crab_visible = |name: &String| {
+            self.crabs
+                .iter()
+                .any(|c| c.name == *name && visible(&c.position))
+        };
+        self.events
+            .iter()
+            .filter(|event| match event {
+                GameEvent::FoodSpawned { position, .. }
+                | GameEvent::CrabSpawned { position, .. }
+                | GameEvent::FoodEaten { position, .. }
+                | GameEvent::CellPainted { position, .. }
+                | GameEvent::PaintOverwritten { position, .. } => visible(position),
+                GameEvent::CrabMoved { from, to, .. } => visible(from) || visible(to),
+                GameEvent::WalkBlocked { name, .. } | GameEvent::CrabRemoved { name } => {
+                    crab_visible(name)
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// カメラが追いかけるカニと、映す範囲
+    fn viewport(&self, camera: &Camera, radius: u32) -> Option<(&Crab, Viewport)> {
+        let crab = match camera {
+            Camera::Follow(name) => self.crabs.iter().find(|c| c.name == *name),
+            Camera::Highlights => self
+                .highlight
+                .as_ref()
+                .and_then(|name| self.crabs.iter().find(|c| c.name == *name))
+                .or_else(|| self.crabs.iter().max_by_key(|c| c.point)),
+        }?;
+        Some((crab, Viewport::around(crab.position, radius, self.size)))
+    }
+
+    /// `token` のカニが知覚できること。`result` は直前に処理したそのカニのコマンドの結果
+    pub fn perception(&self, token: &Token, result: Option<&CommandResult>) -> Option<Perception> {
+        let crab = self.find_crab(token)?;
+        let scan = match result {
+            Some(CommandResult::Scan(scan)) => Some(scan.clone()),
+            _ => None,
+        };
+        Some(Perception {
+            name: crab.name.clone(),
+            point: crab.point,
+            direction: crab.direction,
+            position: crab.position,
+            energy: crab.energy,
+            stage: crab.stage,
+            team: crab.team.clone(),
+            paints: self
+                .paints
+                .iter()
+                .filter(|p| p.crab_token == *token)
+                .map(|p| p.position)
+                .collect(),
+            scan,
+        })
+    }
+
+    /// `token` のカニの位置と向き。ステージやサンドボックスにいるカニは、そちらの GameState で探す
+    pub fn pose(&self, token: &Token) -> Option<(Position, Direction)> {
+        if let Some(crab) = self.find_crab(token) {
+            return Some((crab.position, crab.direction));
         }
+        let sandbox = self.sandboxes.iter().find(|s| s.token == *token);
+        let play = self.stage_plays.iter().find(|p| p.token == *token);
+        sandbox
+            .map(|s| &s.state)
+            .or(play.map(|p| p.state()))
+            .and_then(|state| state.pose(token))
+    }
+
+    /// 直前のコマンドを処理したときに起きたこと
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// `token` のカニが遊んでいるステージの進み具合
+    pub fn stage_progress(&self, token: &Token) -> Option<StageStatusResult> {
+        self.stage_plays
+            .iter()
+            .find(|p| p.token == *token)
+            .map(|p| p.status())
+    }
+
+    /// `token` のカニがいるサンドボックスの状態
+    pub fn sandbox_state(&self, token: &Token) -> Option<&GameState> {
+        self.sandboxes
+            .iter()
+            .find(|s| s.token == *token)
+            .map(|s| &s.state)
+    }
+
+    /// カニ名の統計
+    pub fn crab_stats(&self, name: &str) -> Option<CrabStats> {
+        self.stats.get(name).map(|s| s.snapshot(self.now))
+    }
+
+    /// セッション中に出現したすべてのカニの統計 (名前順)
+    pub fn all_crab_stats(&self) -> Vec<CrabStats> {
+        self.stats.values().map(|s| s.snapshot(self.now)).collect()
+    }
+
+    /// チームの順位表 (順位の高い順)
+    pub fn team_leaderboard(&self) -> Vec<TeamScore> {
+        let scores = self
+            .teams
+            .iter()
+            .map(|(name, hue)| {
+                let members: Vec<&Crab> = self
+                    .crabs
+                    .iter()
+                    .filter(|c| c.team.as_ref() == Some(name))
+                    .collect();
+                let point = match self.config.team.scoring {
+                    TeamScoring::Summed => members.iter().map(|c| c.point).sum(),
+                    TeamScoring::Pooled => members.first().map(|c| c.point).unwrap_or(0),
+                };
+                let paints = self
+                    .paints
+                    .iter()
+                    .filter(|p| members.iter().any(|c| c.token == p.crab_token))
+                    .count();
+                TeamScore {
+                    team: name.clone(),
+                    hue: *hue,
+                    members: members.iter().map(|c| c.name.clone()).collect(),
+                    point,
+                    paints,
+                    rank: 0,
+                }
+            })
+            .collect();
+        team::rank(scores)
+    }
+
+    /// 塗られたマスの集計。ペイントバトルの試合が終わっていれば、終わったときの集計
+    pub fn paint_tally(&self) -> PaintTally {
+        self.paint_battle
+            .as_ref()
+            .and_then(|b| b.tally.clone())
+            .unwrap_or_else(|| paint_battle::tally(self.size, &self.crabs, &self.paints))
+    }
+
+    fn stats_mut(&mut self, token: &Token) -> Option<&mut CrabStats> {
+        let name = &self.crabs.iter().find(|c| c.get_token() == *token)?.name;
+        self.stats.get_mut(name)
     }
 
     #[tracing::instrument(skip(self))]
-    pub(crate) fn proc_command(&mut self, command: &Command) -> CommandResponse {
-        let res = match command {
+    pub fn proc_command(&mut self, command: &Command) -> CommandResponse {
+        self.events.clear();
+        let rotted = self.rot_foods();
+        self.regen_energy();
+        let points: Vec<(Token, i32)> = self.crabs.iter().map(|c| (c.token, c.point)).collect();
+        let mut res = match command {
             Command::PlayerCommand(command) => self.proc_player_command(command),
             Command::GameCycleCommand(command) => self.proc_game_cycle_command(command),
         };
+        self.update_highlight(&points);
+        self.share_team_points(&points);
+        res.mutated |= rotted;
         tracing::event!(tracing::Level::INFO, ?res);
         res
     }
 
     fn proc_game_cycle_command(&mut self, command: &GameCycleCommand) -> CommandResponse {
         match command {
-            GameCycleCommand::SpawnFood => self.spawn_food(),
+            GameCycleCommand::SpawnFood => {
+                for sandbox in &mut self.sandboxes {
+                    sandbox.spawn_food(self.now);
+                }
+                self.spawn_food()
+            }
+            GameCycleCommand::Tick => self.tick(),
+            GameCycleCommand::SpawnNpc(param) => self.spawn(param, true),
+            GameCycleCommand::FeedingFrenzy { duration } => self.feeding_frenzy(*duration),
+            GameCycleCommand::StartPaintBattle => self.start_paint_battle(),
+            GameCycleCommand::Wake => CommandResponse::ok(0, false),
         }
     }
 
     #[tracing::instrument(skip(self))]
     fn spawn_food(&mut self) -> CommandResponse {
-        if self.foods.len() >= 5 {
+        // 大量発生中は設定されたポリシーの代わりに大量発生のポリシーを使う
+        let policy = if self.now < self.frenzy_until {
+            &self.config.food.frenzy
+        } else {
+            &self.config.food.policy
+        };
+        let cap = policy.cap(self.config.food.max_foods, self.crabs.len());
+        if self.foods.len() >= cap {
             return CommandResponse::ok(100, false);
         }
-        let keep_out: Vec<Position> = self
+        let crabs: Vec<Position> = self.crabs.iter().map(|c| c.position).collect();
+        let foods: Vec<Position> = self.foods.iter().map(|f| f.position).collect();
+        let field = FieldView {
+            size: self.size,
+            now: self.now,
+            crabs: &crabs,
+            foods: &foods,
+        };
+        let positions = policy.positions(&field, cap - self.foods.len(), &mut self.rng);
+        let wait = policy.interval();
+        for position in &positions {
+            let kind = self.config.food.weights.choose(&mut self.rng);
+            let mut food = Food::spawn(&mut self.rng, 3, *position, kind);
+            food.rot_at = self.now + self.config.food.rot_interval;
+            self.events.push(GameEvent::FoodSpawned {
+                position: food.position,
+                size: food.size,
+            });
+            self.foods.push(food);
+        }
+        CommandResponse::ok(wait, !positions.is_empty())
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn tick(&mut self) -> CommandResponse {
+        let wait = self.config.mode.tick_interval();
+        let mut mutated = false;
+        if let (Some(ctf), GameMode::CaptureTheFlag { capture_point }) =
+            (self.capture_the_flag.as_mut(), &self.config.mode)
+        {
+            let (scorers, changed) = ctf.tick(&self.crabs);
+            for crab in self.crabs.iter_mut().filter(|c| scorers.contains(&c.token)) {
+                crab.point += capture_point;
+            }
+            mutated |= changed;
+        }
+        if let (
+            Some(koth),
+            GameMode::KingOfTheHill {
+                zones,
+                move_interval,
+                point_per_tick,
+            },
+        ) = (self.king_of_the_hill.as_mut(), &self.config.mode)
+        {
+            let before = koth.clone();
+            let holders = koth.tick(
+                &self.crabs,
+                self.now,
+                self.size,
+                *zones,
+                *move_interval,
+                &mut self.rng,
+            );
+            for crab in self.crabs.iter_mut().filter(|c| holders.contains(&c.token)) {
+                crab.point += point_per_tick;
+            }
+            mutated |= !holders.is_empty() || *koth != before;
+        }
+        if let (Some(battle), GameMode::PaintBattle { match_time, .. }) =
+            (self.paint_battle.as_mut(), &self.config.mode)
+        {
+            let ended = battle.tick(self.now, *match_time);
+            if ended {
+                battle.tally = Some(paint_battle::tally(self.size, &self.crabs, &self.paints));
+            }
+            // 残り時間を配信するため、試合中は毎回状態が変わったことにする
+            mutated |= ended || battle.running();
+        }
+        CommandResponse::ok(wait, mutated)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn start_paint_battle(&mut self) -> CommandResponse {
+        let (Some(battle), GameMode::PaintBattle { match_time, .. }) =
+            (self.paint_battle.as_mut(), &self.config.mode)
+        else {
+            return CommandResponse::ok(0, false);
+        };
+        battle.start(self.now, *match_time);
+        self.paints.clear();
+        CommandResponse::ok(0, true)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn feeding_frenzy(&mut self, duration: u64) -> CommandResponse {
+        self.frenzy_until = self.now.saturating_add(duration);
+        // 次の SpawnFood を待たずに最初の分を置く
+        self.spawn_food()
+    }
+
+    /// 腐りかけのごはんを小さくし、消えたものを取り除く。変化があれば true
+    fn rot_foods(&mut self) -> bool {
+        let (now, interval) = (self.now, self.config.food.rot_interval);
+        let before: Vec<u32> = self.foods.iter().map(|f| f.size).collect();
+        self.foods.retain_mut(|f| f.rot(now, interval));
+        self.foods.len() != before.len()
+            || self
+                .foods
+                .iter()
+                .zip(&before)
+                .any(|(f, size)| f.size != *size)
+    }
+
+    /// すべてのカニのエネルギーを `now` の時点まで回復させる
+    fn regen_energy(&mut self) {
+        let (now, config) = (self.now, &self.config.energy);
+        for crab in &mut self.crabs {
+            let Some(energy) = crab.energy.as_mut() else {
+                continue;
+            };
+            let gained = now.saturating_sub(crab.energy_at) * config.regen_per_sec as u64 / 1000;
+            if *energy >= config.max || config.regen_per_sec == 0 {
+                crab.energy_at = now;
+            } else if gained > 0 {
+                *energy = (*energy + gained as u32).min(config.max);
+                // 端数の時間は次に持ち越す
+                crab.energy_at += gained * 1000 / config.regen_per_sec as u64;
+            }
+        }
+    }
+
+    /// エネルギーを使う。エネルギーが 0 で行動できなければ false
+    fn use_energy(&mut self, command: &PlayerCommand) -> bool {
+        let cost = self.config.energy.cost(command);
+        let Some(crab) = command.token().and_then(|t| self.find_crab_mut(&t)) else {
+            return true;
+        };
+        match crab.energy.as_mut() {
+            Some(0) if cost > 0 => false,
+            Some(energy) => {
+                *energy = energy.saturating_sub(cost);
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// ポイントを共有するルールで、ポイントが増減したカニのチームメイトにも同じポイントを反映する
+    fn share_team_points(&mut self, before: &[(Token, i32)]) {
+        if self.config.team.scoring != TeamScoring::Pooled {
+            return;
+        }
+        // 同じコマンドで何匹も増減することがある (Tick の得点、チーム間の Pinch) ので、
+        // チームごとに増減を合計してからチームメイト全員に反映する
+        let mut pooled: BTreeMap<String, (i32, i32)> = BTreeMap::new();
+        for crab in &self.crabs {
+            let Some(team) = &crab.team else {
+                continue;
+            };
+            let Some((_, point)) = before.iter().find(|(token, _)| *token == crab.token) else {
+                continue;
+            };
+            pooled.entry(team.clone()).or_insert((*point, 0)).1 += crab.point - point;
+        }
+        for crab in &mut self.crabs {
+            let Some((point, delta)) = crab.team.as_ref().and_then(|team| pooled.get(team)) else {
+                continue;
+            };
+            if *delta != 0 {
+                crab.point = (point + delta).max(0);
+            }
+        }
+    }
+
+    /// いちばん多くポイントを増やしたカニをハイライトにする
+    fn update_highlight(&mut self, before: &[(Token, i32)]) {
+        let scorer = self
             .crabs
             .iter()
-            .map(|c| c.position)
-            .chain(self.foods.iter().map(|f| f.position))
-            .collect();
-        let food = Food::spawn(3, self.size, &keep_out);
-        self.foods.push(food.clone());
-        CommandResponse::ok(5000, true)
+            .filter_map(|crab| {
+                let (_, point) = before.iter().find(|(token, _)| *token == crab.token)?;
+                Some((crab.point - point, crab))
+            })
+            .filter(|(gain, _)| *gain > 0)
+            .max_by_key(|(gain, _)| *gain);
+        if let Some((_, crab)) = scorer {
+            self.highlight = Some(crab.name.clone());
+        }
     }
 
     fn proc_player_command(&mut self, command: &PlayerCommand) -> CommandResponse {
+        // サンドボックスにいるカニのコマンドは、サンドボックスの GameState で処理する
+        let sandbox = command
+            .token()
+            .and_then(|token| self.sandboxes.iter().position(|s| s.token == token));
+        if let Some(index) = sandbox {
+            return match command {
+                PlayerCommand::ResetSandbox(_) => {
+                    let seed = self.rng.gen();
+                    let sandbox = &mut self.sandboxes[index];
+                    sandbox.reset(seed);
+                    let mut res = CommandResponse::spawn(SpawnResult {
+                        token: sandbox.token,
+                    });
+                    res.mutated = false;
+                    res
+                }
+                PlayerCommand::Graduate(_) => self.graduate(index),
+                _ => self.sandboxes[index].proc_command(command, self.now),
+            };
+        }
+        // ステージを遊んでいるカニのコマンドは、ステージの GameState で処理する
+        let playing = command.token().and_then(|token| {
+            self.stage_plays
+                .iter_mut()
+                .find(|p| p.token == token)
+                .filter(|_| !matches!(command, PlayerCommand::StartStage(_)))
+        });
+        if let Some(play) = playing {
+            return match command {
+                PlayerCommand::StageStatus(_) => CommandResponse::stage_status(play.status()),
+                _ => play.proc_command(command, self.now),
+            };
+        }
+        let mut res = if self.is_stunned(command) {
+            // 動けない間はフィールドを変えるコマンドを受け付けない。待ち時間は下で足す
+            CommandResponse::command_not_allowed()
+        } else if self.use_energy(command) {
+            self.dispatch_player_command(command)
+        } else {
+            CommandResponse::out_of_energy(1000 / self.config.energy.regen_per_sec.max(1) as u64)
+        };
+        let Some(token) = command.token() else {
+            return res;
+        };
+        // 動けなくなっている間は結果を返すのを遅らせる
+        if let Some(crab) = self.find_crab(&token) {
+            res.wait += crab.stunned_until.saturating_sub(self.now);
+        }
+        if let Some(stats) = self.stats_mut(&token) {
+            stats.record(command, &res.result);
+        }
+        res
+    }
+
+    /// 動けなくなっているカニが、旋回・移動・ペイント・はさむのどれかをしようとしているか
+    fn is_stunned(&self, command: &PlayerCommand) -> bool {
+        let acts = matches!(
+            command,
+            PlayerCommand::Turn(_)
+                | PlayerCommand::Walk(_)
+                | PlayerCommand::Paint(_)
+                | PlayerCommand::Pinch(_)
+        );
+        acts && command
+            .token()
+            .and_then(|token| self.find_crab(&token))
+            .is_some_and(|crab| self.now < crab.stunned_until)
+    }
+
+    fn dispatch_player_command(&mut self, command: &PlayerCommand) -> CommandResponse {
         match command {
             PlayerCommand::Ping => CommandResponse::pong(),
-            PlayerCommand::Spawn(param) => self.spawn(param),
+            PlayerCommand::Spawn(param) => self.spawn(param, false),
             PlayerCommand::Turn(param) => self.turn(param),
             PlayerCommand::Walk(param) => self.walk(param),
             PlayerCommand::Scan(param) => self.scan(param),
             PlayerCommand::Paint(param) => self.paint(param),
+            PlayerCommand::Pinch(param) => self.pinch(param),
+            PlayerCommand::StartStage(param) => self.start_stage(param),
+            PlayerCommand::StageStatus(param) => self.stage_status(param),
+            PlayerCommand::ResetSandbox(_) | PlayerCommand::Graduate(_) => {
+                CommandResponse::sandbox_not_found()
+            }
+            PlayerCommand::WaitFor(param) => self.wait_for(param),
+        }
+    }
+
+    /// GameState だけではその場で待てないので、今の様子を返す。
+    /// サーバーでは command_processor が `watch` で様子を残して、何か起きるまで結果を返さずに待つ
+    fn wait_for(&self, param: &WaitForParam) -> CommandResponse {
+        match self.wait_for_result(&param.token, WaitReason::Timeout) {
+            Some(result) => CommandResponse::wait_for(result),
+            None => CommandResponse::crab_not_found(),
+        }
+    }
+
+    pub(crate) fn wait_for_result(
+        &self,
+        token: &Token,
+        reason: WaitReason,
+    ) -> Option<WaitForResult> {
+        let crab = self.find_crab(token)?;
+        let CommandResult::Scan(scan) = self.scan(&ScanParam { token: *token }).result else {
+            return None;
+        };
+        Some(WaitForResult {
+            reason,
+            scan,
+            total_point: crab.point,
+        })
+    }
+
+    /// WaitFor で待ち始めるときの様子。フィールドにいないカニは待てない
+    pub fn watch(&self, param: &WaitForParam) -> Option<Watch> {
+        let result = self.wait_for_result(&param.token, WaitReason::Timeout)?;
+        Some(Watch::new(param, result.scan, result.total_point, self.now))
+    }
+
+    /// 同じ名前のカニをフィールドから除去する。ペイントも削除する
+    fn remove_crab(&mut self, name: &str) {
+        let Some(index) = self.crabs.iter().position(|c| c.name == name) else {
+            return;
+        };
+        let old_token = self.crabs[index].get_token();
+        self.crabs.remove(index);
+        self.events.push(GameEvent::CrabRemoved {
+            name: name.to_string(),
+        });
+        if let Some(stats) = self.stats.get_mut(name) {
+            stats.removed(self.now);
         }
+        // 除去したカニのペイントも削除
+        self.paints = self
+            .paints
+            .iter()
+            .filter(|p| p.crab_token != old_token)
+            .cloned()
+            .collect();
     }
 
     #[tracing::instrument(skip(self))]
-    fn spawn(&mut self, param: &SpawnParam) -> CommandResponse {
+    fn spawn(&mut self, param: &SpawnParam, npc: bool) -> CommandResponse {
+        self.spawn_with_token(param, npc, Token::new())
+    }
+
+    fn spawn_with_token(&mut self, param: &SpawnParam, npc: bool, token: Token) -> CommandResponse {
+        // キャプチャー・ザ・フラッグで新しいチームの陣地を置く場所がなければ、出現させない
+        if let (Some(team), Some(ctf), None) = (&param.team, &self.capture_the_flag, param.sandbox)
+        {
+            if !ctf.can_join(team, self.size) {
+                return CommandResponse::command_not_allowed();
+            }
+        }
         // すでに同じ名前のカニがいる場合は、同じ名前のカニを除去する
         // 除去せずエラーとするほうが安全だが、プログラミングハンズオンの性質的にリトライのしやすさを優先する
-        if let Some(index) = self.crabs.iter().position(|c| c.name == param.name) {
-            let old_token = self.crabs[index].get_token();
-            self.crabs.remove(index);
-            // 除去したカニのペイントも削除
-            self.paints = self
-                .paints
-                .iter()
-                .filter(|p| p.crab_token != old_token)
-                .cloned()
-                .collect();
+        // ステージやサンドボックスにいるカニも、Spawn し直すとフィールドに戻る
+        self.remove_crab(&param.name);
+        self.stage_plays.retain(|p| p.name != param.name);
+        self.sandboxes.retain(|s| s.name != param.name);
+        if let Some(mode) = param.sandbox {
+            let seed = self.rng.gen();
+            let sandbox = Sandbox::new(token, param, mode, self.size, &self.config, seed);
+            self.sandboxes.push(sandbox);
+            return CommandResponse::spawn(SpawnResult { token });
         }
         let keep_out: Vec<Position> = self
             .crabs
@@ -93,11 +802,80 @@ impl GameState {
             .map(|c| c.position)
             .chain(self.foods.iter().map(|f| f.position))
             .collect();
-        let crab = Crab::spawn(&param.name, param.hue, self.size, &keep_out);
+        let mut crab = Crab::spawn(&mut self.rng, &param.name, param.hue, self.size, &keep_out);
+        crab.token = token;
+        crab.npc = npc;
+        if let Some(team) = &param.team {
+            // チームの色で塗れるように、カニの色相をチームの色相にそろえる
+            crab.hue = *self.teams.entry(team.clone()).or_insert(param.hue);
+            crab.team = Some(team.clone());
+            if let Some(ctf) = self.capture_the_flag.as_mut() {
+                ctf.join(team, self.size, &mut self.rng);
+            }
+            // ポイントを共有するルールでは、途中から入ってもチームのポイントを引き継ぐ
+            if self.config.team.scoring == TeamScoring::Pooled {
+                if let Some(mate) = self.crabs.iter().find(|c| c.team == crab.team) {
+                    crab.point = mate.point;
+                }
+            }
+        }
+        if self.config.energy.enabled {
+            crab.energy = Some(self.config.energy.max);
+            crab.energy_at = self.now;
+        }
+        self.stats
+            .entry(param.name.clone())
+            .or_insert_with(|| CrabStats::new(&param.name, npc))
+            .spawned(self.now);
+        self.events.push(GameEvent::CrabSpawned {
+            name: crab.name.clone(),
+            position: crab.position,
+        });
         self.crabs.push(crab.clone());
         CommandResponse::spawn(crab.into())
     }
 
+    /// サンドボックスを卒業して、同じトークンのままフィールドに出現する
+    fn graduate(&mut self, index: usize) -> CommandResponse {
+        let sandbox = self.sandboxes.remove(index);
+        let param = SpawnParam {
+            team: sandbox.team,
+            ..SpawnParam::new(&sandbox.name, sandbox.hue)
+        };
+        self.spawn_with_token(&param, false, sandbox.token)
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn start_stage(&mut self, param: &StartStageParam) -> CommandResponse {
+        let Some(stage) = stage::find(&param.stage) else {
+            return CommandResponse::stage_not_found();
+        };
+        // ステージを遊んでいる途中なら、はじめからやり直す
+        let (name, hue) = if let Some(crab) = self.find_crab(&param.token) {
+            (crab.name.clone(), crab.hue)
+        } else if let Some(play) = self.stage_plays.iter().find(|p| p.token == param.token) {
+            (play.name.clone(), play.hue)
+        } else {
+            return CommandResponse::crab_not_found();
+        };
+        self.remove_crab(&name);
+        self.stage_plays.retain(|p| p.token != param.token);
+        let seed = self.rng.gen();
+        let play = StagePlay::new(param.token, &name, hue, stage, seed);
+        let mut res = CommandResponse::stage_status(play.status());
+        res.mutated = true;
+        self.stage_plays.push(play);
+        res
+    }
+
+    /// フィールドにいるカニはステージを遊んでいない
+    fn stage_status(&self, param: &StageStatusParam) -> CommandResponse {
+        if self.find_crab(&param.token).is_none() {
+            return CommandResponse::crab_not_found();
+        }
+        CommandResponse::stage_not_found()
+    }
+
     fn find_crab_mut(&mut self, token: &Token) -> Option<&mut Crab> {
         self.crabs.iter_mut().find(|c| c.get_token() == *token)
     }
@@ -121,23 +899,152 @@ impl GameState {
         let Some(crab) = self.find_crab(&param.token) else {
             return CommandResponse::crab_not_found();
         };
+        let from = crab.position;
         let new_pos = crab.walk(param.side).position;
-        if !new_pos.is_inset(size, size) || self.find_crab_by_position(&new_pos).is_some() {
+        let blocked = if !new_pos.is_inset(size, size) || self.rocks.contains(&new_pos) {
+            Some(WalkBlocked::Wall)
+        } else if let Some(other) = self.find_crab_by_position(&new_pos) {
+            // 押し出せるルームでは、ぶつかったカニをその先の空いているマスへ押し出す
+            let other = other.token;
+            let behind = Position::new(new_pos.x * 2 - from.x, new_pos.y * 2 - from.y);
+            let free = behind.is_inset(size, size)
+                && !self.rocks.contains(&behind)
+                && self.find_crab_by_position(&behind).is_none()
+                && !self.foods.iter().any(|f| f.position == behind);
+            if self.config.crab.shove && free {
+                self.move_crab(&other, behind);
+                None
+            } else {
+                Some(WalkBlocked::Crab)
+            }
+        } else {
+            None
+        };
+        let crab = self.find_crab(&param.token).unwrap();
+        if let Some(blocked) = blocked {
+            let (total_point, energy) = (crab.point, crab.energy);
+            self.events.push(GameEvent::WalkBlocked {
+                name: crab.name.clone(),
+                by: blocked,
+            });
+            if let Some(stats) = self.stats_mut(&param.token) {
+                stats.walk_blocked(blocked);
+            }
             return CommandResponse::walk(WalkResult {
                 success: false,
                 point: 0,
-                total_point: crab.point,
+                total_point,
+                energy,
             });
         }
         let food = self.take_food_by_position(&new_pos);
-        let crab = self.find_crab_mut(&param.token).unwrap();
-        crab.walk_mut(param.side);
+        if let (Some(food), Some(stats)) = (&food, self.stats_mut(&param.token)) {
+            stats.ate(food.size, food.kind);
+        }
+        let poison_effect = self.config.food.poison_effect;
+        let stunned_until = self.now + self.config.food.stun_wait;
+        self.move_crab(&param.token, new_pos);
+        let energy = &self.config.energy;
+        let growth = &self.config.growth;
+        let crab = self
+            .crabs
+            .iter_mut()
+            .find(|c| c.get_token() == param.token)
+            .unwrap();
         if let Some(food) = &food {
-            crab.point += food.size as i32;
+            self.events.push(GameEvent::FoodEaten {
+                name: crab.name.clone(),
+                position: food.position,
+                size: food.size,
+            });
+        }
+        let before = crab.point;
+        // ごはんを食べるとエネルギーも回復する
+        if let (Some(food), Some(e)) = (&food, crab.energy.as_mut()) {
+            *e = (*e + food.size * energy.per_food_size).min(energy.max);
+        }
+        // 大きいカニほど歩くのが遅い
+        let slowdown = growth.walk_slowdown * crab.stage as u64;
+        if food.is_some() && growth.enabled && crab.stage < growth.max_stage {
+            crab.molt_progress += 1;
+            if crab.molt_progress >= growth.foods_per_stage {
+                crab.stage += 1;
+                crab.molt_progress = 0;
+            }
+        }
+        match &food {
+            Some(food) if food.kind == FoodKind::Poison && poison_effect == PoisonEffect::Stun => {
+                crab.stunned_until = stunned_until;
+            }
+            // 毒でポイントが減ってもマイナスにはしない
+            Some(food) => crab.point = (crab.point + food.point()).max(0),
+            None => {}
+        }
+        let mut res = CommandResponse::walk(WalkResult {
+            success: true,
+            point: crab.point - before,
+            total_point: crab.point,
+            energy: crab.energy,
+        });
+        res.wait += slowdown;
+        res
+    }
+
+    /// 歩いたり押し出されたりしたカニを `to` に動かす。旗を持っていれば旗も一緒に動き、相手の旗の上なら拾う
+    fn move_crab(&mut self, token: &Token, to: Position) {
+        let Some(crab) = self.crabs.iter_mut().find(|c| c.token == *token) else {
+            return;
+        };
+        let from = crab.position;
+        crab.position = to;
+        self.events.push(GameEvent::CrabMoved {
+            name: crab.name.clone(),
+            from,
+            to,
+        });
+        if let Some(ctf) = self.capture_the_flag.as_mut() {
+            ctf.walked(crab);
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn pinch(&mut self, param: &PinchParam) -> CommandResponse {
+        let Some(crab) = self.find_crab(&param.token) else {
+            return CommandResponse::crab_not_found();
+        };
+        let front = crab.position.forward(crab.direction);
+        let team = crab.team.clone();
+        let effect = self.config.crab.pinch;
+        let stunned_until = self.now + self.config.crab.stun_wait;
+        let Some(target) = self
+            .crabs
+            .iter_mut()
+            .find(|c| c.position == front)
+            // チームメイトははさめない
+            .filter(|c| team.is_none() || c.team != team)
+            .filter(|_| effect != PinchEffect::Disabled)
+        else {
+            let total_point = self.find_crab(&param.token).unwrap().point;
+            return CommandResponse::pinch(PinchResult {
+                success: false,
+                stolen: 0,
+                total_point,
+            });
+        };
+        let mut stolen = 0;
+        match effect {
+            PinchEffect::Steal if target.point > 0 => {
+                target.point -= 1;
+                stolen = 1;
+            }
+            PinchEffect::Stun => target.stunned_until = stunned_until,
+            _ => {}
         }
-        CommandResponse::walk(WalkResult {
+        let crab = self.find_crab_mut(&param.token).unwrap();
+        crab.point += stolen;
+        CommandResponse::pinch(PinchResult {
             success: true,
-            point: food.map(|f| f.size as i32).unwrap_or(0),
+            stolen,
             total_point: crab.point,
         })
     }
@@ -150,21 +1057,60 @@ impl GameState {
         };
         let mut pos = crab.position;
         let direction = crab.direction;
+        let mut zone = None;
         while pos.is_inset(size, size) {
             pos = pos.forward(direction);
+            // 岩の向こうは見えない
+            if self.rocks.contains(&pos) {
+                break;
+            }
             if self.find_crab_by_position(&pos).is_some() {
                 return CommandResponse::scan(ScanResult {
                     what_you_can_see: WhatYouCanSee::Crab,
+                    food_kind: None,
+                    team: None,
                 });
             }
-            if self.foods.iter().any(|f| f.position == pos) {
+            if let Some(ctf) = &self.capture_the_flag {
+                if let Some(team) = ctf.flag_at(&pos) {
+                    return CommandResponse::scan(ScanResult {
+                        what_you_can_see: WhatYouCanSee::Flag,
+                        food_kind: None,
+                        team: Some(team.to_string()),
+                    });
+                }
+                // 陣地は見通せるので、ほかに何も見えなかったときだけ陣地が見えたことにする
+                if zone.is_none() {
+                    zone = ctf
+                        .base_at(&pos, &crab.position)
+                        .map(|team| (WhatYouCanSee::Base, Some(team.to_string())));
+                }
+            }
+            if let Some(koth) = &self.king_of_the_hill {
+                // 丘も陣地と同じく見通せる
+                if zone.is_none() && koth.hill_at(&pos, &crab.position) {
+                    zone = Some((WhatYouCanSee::Hill, None));
+                }
+            }
+            if let Some(food) = self.foods.iter().find(|f| f.position == pos) {
                 return CommandResponse::scan(ScanResult {
                     what_you_can_see: WhatYouCanSee::Food,
+                    food_kind: self.config.food.reveal_kind.then_some(food.kind),
+                    team: None,
                 });
             }
         }
+        if let Some((what_you_can_see, team)) = zone {
+            return CommandResponse::scan(ScanResult {
+                what_you_can_see,
+                food_kind: None,
+                team,
+            });
+        }
         CommandResponse::scan(ScanResult {
             what_you_can_see: WhatYouCanSee::Wall,
+            food_kind: None,
+            team: None,
         })
     }
 
@@ -175,42 +1121,127 @@ impl GameState {
         };
         // ポイントがない場合は失敗
         if crab.point <= 0 {
-            return CommandResponse::paint(PaintResult {
-                success: false,
-                your_paints: self
-                    .paints
-                    .iter()
-                    .filter(|p| p.crab_token == param.token)
-                    .map(|p| p.position)
-                    .collect(),
-                total_point: 0,
+            return self.paint_failed(crab);
+        }
+        // 十分に成長したカニはまわりの 3x3 マスを一度に塗る
+        let size = self.size as i32;
+        let center = crab.position;
+        let mut area: Vec<Position> = if self.config.growth.enabled
+            && crab.stage >= self.config.growth.wide_paint_stage
+        {
+            (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| Position::new(center.x + dx, center.y + dy)))
+                .filter(|p| p.is_inset(size, size))
+                .collect()
+        } else {
+            vec![center]
+        };
+        // チームメイトが塗ったマスは塗り替えない。塗れるマスがなければ失敗
+        let mates: Vec<Token> = self
+            .crabs
+            .iter()
+            .filter(|c| c.team.is_some() && c.team == crab.team && c.token != crab.token)
+            .map(|c| c.token)
+            .collect();
+        area.retain(|position| {
+            !self
+                .paints
+                .iter()
+                .any(|p| p.position == *position && mates.contains(&p.crab_token))
+        });
+        let mut cost = 1;
+        let mut protected = vec![];
+        if let GameMode::PaintBattle { overwrite_cost, .. } = self.config.mode {
+            // 試合中でなければ塗れない
+            if !self.paint_battle.as_ref().is_some_and(|b| b.running()) {
+                return self.paint_failed(crab);
+            }
+            // 守られている相手のマスは塗り替えない。自分のマスを重ね塗りすると守られる
+            let owner = |position: &Position| self.paints.iter().find(|p| p.position == *position);
+            area.retain(|position| {
+                !owner(position).is_some_and(|p| p.protected && p.crab_token != crab.token)
             });
+            cost = area
+                .iter()
+                .map(|position| match owner(position) {
+                    Some(p) if p.crab_token != crab.token => overwrite_cost,
+                    _ => 1,
+                })
+                .sum();
+            protected = area
+                .iter()
+                .filter(|position| owner(position).is_some_and(|p| p.crab_token == crab.token))
+                .copied()
+                .collect();
+        }
+        if area.is_empty() || crab.point < cost {
+            return self.paint_failed(crab);
         }
-        let paint = Paint::new(crab.position, crab.token, crab.hue);
+        let (token, hue) = (crab.token, crab.hue);
+        let events: Vec<GameEvent> = area
+            .iter()
+            .map(|&position| {
+                let name = crab.name.clone();
+                let previous = self
+                    .paints
+                    .iter()
+                    .find(|p| p.position == position && p.crab_token != token)
+                    .and_then(|p| self.find_crab(&p.crab_token));
+                match previous {
+                    Some(previous) => GameEvent::PaintOverwritten {
+                        name,
+                        position,
+                        previous: previous.name.clone(),
+                    },
+                    None => GameEvent::CellPainted { name, position },
+                }
+            })
+            .collect();
+        self.events.extend(events);
         // 同じ位置にあるペイントを削除
-        if let Some(index) = self.paints.iter().position(|p| p.position == crab.position) {
-            self.paints.remove(index);
-        };
-        self.paints.push(paint.clone());
+        self.paints.retain(|p| !area.contains(&p.position));
+        self.paints.extend(area.into_iter().map(|position| Paint {
+            protected: protected.contains(&position),
+            ..Paint::new(position, token, hue)
+        }));
         let paints = self
             .paints
             .iter()
             .filter(|p| p.crab_token == param.token)
             .map(|p| p.position)
             .collect();
+        if let Some(stats) = self.stats_mut(&param.token) {
+            stats.painted(cost);
+        }
         let crab = self.find_crab_mut(&param.token).unwrap();
-        crab.point -= 1;
+        crab.point -= cost;
         CommandResponse::paint(PaintResult {
             success: true,
             your_paints: paints,
             total_point: crab.point,
+            energy: crab.energy,
         })
     }
 
-    fn find_crab_by_position(&self, position: &Position) -> Option<&Crab> {
-        self.crabs.iter().find(|c| c.position == *position)
-    }
-
+    /// 塗らずに失敗した Paint の結果
+    fn paint_failed(&self, crab: &Crab) -> CommandResponse {
+        CommandResponse::paint(PaintResult {
+            success: false,
+            your_paints: self
+                .paints
+                .iter()
+                .filter(|p| p.crab_token == crab.token)
+                .map(|p| p.position)
+                .collect(),
+            total_point: crab.point,
+            energy: crab.energy,
+        })
+    }
+
+    fn find_crab_by_position(&self, position: &Position) -> Option<&Crab> {
+        self.crabs.iter().find(|c| c.position == *position)
+    }
+
     fn take_food_by_position(&mut self, position: &Position) -> Option<Food> {
         let index = self.foods.iter().position(|f| f.position == *position);
         if let Some(index) = index {
@@ -228,6 +1259,15 @@ mod tests {
     };
     use crate::command::CommandResult;
     use crate::geometry::{Direction, Side};
+    use crate::stats::markdown_report;
+
+    fn spawn(state: &mut GameState, param: SpawnParam) -> Token {
+        let command = Command::PlayerCommand(PlayerCommand::Spawn(param));
+        match state.proc_command(&command).result {
+            CommandResult::Spawn(result) => result.token,
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
 
     #[test]
     fn test_crab_collides_to_wall() {
@@ -246,9 +1286,17 @@ mod tests {
                 point: 0,
                 direction: Direction::N,
                 position: Position::new(0, 0),
+                npc: false,
+                stunned_until: 0,
+                energy: None,
+                energy_at: 0,
+                stage: 0,
+                molt_progress: 0,
+                team: None,
             }],
             foods: vec![],
             paints: vec![],
+            ..GameState::new(2)
         };
 
         // 右に一度移動できる
@@ -263,6 +1311,7 @@ mod tests {
                 success: true,
                 point: 0,
                 total_point: 0,
+                energy: None,
             })
         );
         assert_eq!(state.crabs[0].position, Position::new(1, 0));
@@ -274,6 +1323,7 @@ mod tests {
                 success: false,
                 point: 0,
                 total_point: 0,
+                energy: None,
             })
         );
         assert_eq!(state.crabs[0].position, Position::new(1, 0));
@@ -300,6 +1350,13 @@ mod tests {
                     point: 0,
                     direction: Direction::N,
                     position: Position::new(0, 0),
+                    npc: false,
+                    stunned_until: 0,
+                    energy: None,
+                    energy_at: 0,
+                    stage: 0,
+                    molt_progress: 0,
+                    team: None,
                 },
                 Crab {
                     name: "other".to_string(),
@@ -308,10 +1365,18 @@ mod tests {
                     point: 0,
                     direction: Direction::N,
                     position: Position::new(2, 0),
+                    npc: false,
+                    stunned_until: 0,
+                    energy: None,
+                    energy_at: 0,
+                    stage: 0,
+                    molt_progress: 0,
+                    team: None,
                 },
             ],
             foods: vec![],
             paints: vec![],
+            ..GameState::new(3)
         };
 
         // Act
@@ -328,6 +1393,7 @@ mod tests {
                 success: true,
                 point: 0,
                 total_point: 0,
+                energy: None,
             })
         );
         assert_eq!(state.crabs[0].position, Position::new(1, 0));
@@ -339,6 +1405,7 @@ mod tests {
                 success: false,
                 point: 0,
                 total_point: 0,
+                energy: None,
             })
         );
         assert_eq!(state.crabs[0].position, Position::new(1, 0));
@@ -354,6 +1421,7 @@ mod tests {
                 success: true,
                 point: 0,
                 total_point: 0,
+                energy: None,
             })
         );
         assert_eq!(state.crabs[0].position, Position::new(0, 0));
@@ -376,13 +1444,23 @@ mod tests {
                 point: 0,
                 direction: Direction::N,
                 position: Position::new(0, 0),
+                npc: false,
+                stunned_until: 0,
+                energy: None,
+                energy_at: 0,
+                stage: 0,
+                molt_progress: 0,
+                team: None,
             }],
             foods: vec![Food {
                 id: Token::new(),
                 position: Position::new(1, 0),
                 size: 1,
+                kind: FoodKind::Normal,
+                rot_at: 0,
             }],
             paints: vec![],
+            ..GameState::new(2)
         };
 
         // Act
@@ -399,6 +1477,7 @@ mod tests {
                 success: true,
                 point: 1,
                 total_point: 1,
+                energy: None,
             })
         );
         assert_eq!(state.crabs[0].position, Position::new(1, 0));
@@ -423,9 +1502,17 @@ mod tests {
                 point: 0,
                 direction: Direction::N,
                 position: Position::new(1, 0),
+                npc: false,
+                stunned_until: 0,
+                energy: None,
+                energy_at: 0,
+                stage: 0,
+                molt_progress: 0,
+                team: None,
             }],
             foods: vec![],
             paints: vec![],
+            ..GameState::new(2)
         };
 
         // Act
@@ -452,6 +1539,7 @@ mod tests {
                 success: true,
                 point: 0,
                 total_point: 0,
+                energy: None,
             })
         );
         assert_eq!(state.crabs[0].direction, Direction::E);
@@ -479,6 +1567,7 @@ mod tests {
                 success: true,
                 point: 0,
                 total_point: 0,
+                energy: None,
             })
         );
         assert_eq!(state.crabs[0].direction, Direction::S);
@@ -505,6 +1594,13 @@ mod tests {
                     point: 0,
                     direction: Direction::N,
                     position: Position::new(0, 2),
+                    npc: false,
+                    stunned_until: 0,
+                    energy: None,
+                    energy_at: 0,
+                    stage: 0,
+                    molt_progress: 0,
+                    team: None,
                 },
                 Crab {
                     name: "other".to_string(),
@@ -513,14 +1609,24 @@ mod tests {
                     point: 0,
                     direction: Direction::N,
                     position: Position::new(1, 0),
+                    npc: false,
+                    stunned_until: 0,
+                    energy: None,
+                    energy_at: 0,
+                    stage: 0,
+                    molt_progress: 0,
+                    team: None,
                 },
             ],
             foods: vec![Food {
                 id: Token::new(),
                 position: Position::new(2, 1),
                 size: 1,
+                kind: FoodKind::Normal,
+                rot_at: 0,
             }],
             paints: vec![],
+            ..GameState::new(3)
         };
 
         // Act
@@ -531,7 +1637,9 @@ mod tests {
         assert_eq!(
             response.result,
             CommandResult::Scan(ScanResult {
-                what_you_can_see: WhatYouCanSee::Wall
+                what_you_can_see: WhatYouCanSee::Wall,
+                food_kind: None,
+                team: None,
             })
         );
 
@@ -548,7 +1656,9 @@ mod tests {
         assert_eq!(
             response.result,
             CommandResult::Scan(ScanResult {
-                what_you_can_see: WhatYouCanSee::Crab
+                what_you_can_see: WhatYouCanSee::Crab,
+                food_kind: None,
+                team: None,
             })
         );
 
@@ -565,7 +1675,9 @@ mod tests {
         assert_eq!(
             response.result,
             CommandResult::Scan(ScanResult {
-                what_you_can_see: WhatYouCanSee::Food
+                what_you_can_see: WhatYouCanSee::Food,
+                food_kind: None,
+                team: None,
             })
         );
     }
@@ -589,13 +1701,23 @@ mod tests {
                 point: 0,
                 direction: Direction::S,
                 position: Position::new(0, 0),
+                npc: false,
+                stunned_until: 0,
+                energy: None,
+                energy_at: 0,
+                stage: 0,
+                molt_progress: 0,
+                team: None,
             }],
             foods: vec![Food {
                 id: Token::new(),
                 position: Position::new(0, 2),
                 size: 1,
+                kind: FoodKind::Normal,
+                rot_at: 0,
             }],
             paints: vec![],
+            ..GameState::new(3)
         };
 
         // Act
@@ -606,7 +1728,9 @@ mod tests {
         assert_eq!(
             response.result,
             CommandResult::Scan(ScanResult {
-                what_you_can_see: WhatYouCanSee::Food
+                what_you_can_see: WhatYouCanSee::Food,
+                food_kind: None,
+                team: None,
             })
         );
     }
@@ -628,9 +1752,17 @@ mod tests {
                 point: 1,
                 direction: Direction::N,
                 position: Position::new(0, 0),
+                npc: false,
+                stunned_until: 0,
+                energy: None,
+                energy_at: 0,
+                stage: 0,
+                molt_progress: 0,
+                team: None,
             }],
             foods: vec![],
             paints: vec![],
+            ..GameState::new(2)
         };
 
         // Act
@@ -644,6 +1776,7 @@ mod tests {
                 success: true,
                 your_paints: vec![Position::new(0, 0)],
                 total_point: 0,
+                energy: None,
             })
         );
 
@@ -664,7 +1797,1098 @@ mod tests {
                 success: false,
                 your_paints: vec![Position::new(0, 0)],
                 total_point: 0,
+                energy: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_npc_is_marked() {
+        let mut state = GameState::new(3);
+        let param = SpawnParam {
+            name: "npc".to_string(),
+            hue: 0.0,
+            team: None,
+            sandbox: None,
+        };
+        let command = Command::GameCycleCommand(GameCycleCommand::SpawnNpc(param.clone()));
+        let response = state.proc_command(&command);
+        assert!(matches!(response.result, CommandResult::Spawn(_)));
+        let command = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam {
+            name: "player".to_string(),
+            ..param
+        }));
+        let _ = state.proc_command(&command);
+
+        // 配信される状態で NPC かどうかわかる
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["crabs"][0]["npc"], true);
+        assert_eq!(json["crabs"][1]["npc"], false);
+    }
+
+    #[test]
+    fn test_stats_are_recorded() {
+        let token = Token::new();
+        //  +----+----+
+        //  | 🦀 | 🍙 |  <- player and food
+        //  +----+----+
+        let mut stats = CrabStats::new("player", false);
+        stats.spawned(0);
+        let mut state = GameState {
+            size: 2,
+            crabs: vec![Crab {
+                name: "player".to_string(),
+                token,
+                hue: 0.0,
+                point: 0,
+                direction: Direction::N,
+                position: Position::new(0, 0),
+                npc: false,
+                stunned_until: 0,
+                energy: None,
+                energy_at: 0,
+                stage: 0,
+                molt_progress: 0,
+                team: None,
+            }],
+            foods: vec![Food {
+                id: Token::new(),
+                position: Position::new(1, 0),
+                size: 2,
+                kind: FoodKind::Normal,
+                rot_at: 0,
+            }],
+            paints: vec![],
+            stats: BTreeMap::from([("player".to_string(), stats)]),
+            ..GameState::new(2)
+        };
+        let walk = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token,
+            side: Side::Right,
+        }));
+        let paint = Command::PlayerCommand(PlayerCommand::Paint(PaintParam { token }));
+
+        // ごはんを食べて、壁にぶつかって、2 回塗る。ないステージを始めようとしても失敗になる
+        state.set_now(1000);
+        state.proc_command(&walk);
+        state.proc_command(&walk);
+        state.proc_command(&paint);
+        state.proc_command(&paint);
+        state.proc_command(&Command::PlayerCommand(PlayerCommand::StartStage(
+            StartStageParam {
+                token,
+                stage: "no-such-stage".to_string(),
+            },
+        )));
+        state.set_now(3000);
+
+        let stats = state.crab_stats("player").unwrap();
+        assert_eq!(stats.commands["Walk"], 2);
+        assert_eq!(stats.commands["Paint"], 2);
+        assert_eq!(stats.walk_success, 1);
+        assert_eq!(stats.walk_blocked_by_wall, 1);
+        assert_eq!(stats.walk_blocked_by_crab, 0);
+        assert_eq!(stats.foods_eaten[&2], 1);
+        assert_eq!(stats.points_spent_on_paint, 2);
+        assert_eq!(stats.time_alive, 3000);
+        // 壁にぶつかった後の 2 回が最長。失敗した StartStage で途切れる
+        assert_eq!(stats.longest_streak, 2);
+        assert_eq!(stats.commands["StartStage"], 1);
+        assert!(markdown_report(&state.all_crab_stats()).contains("| player | 5 |"));
+    }
+
+    #[test]
+    fn test_food_kinds() {
+        let token = Token::new();
+        //  +----+----+----+
+        //  | 🦀 | 🌟 | ☠️ |  <- player, golden food and poison food
+        //  +----+----+----+
+        let food = |x, kind| Food {
+            id: Token::new(),
+            position: Position::new(x, 0),
+            size: 2,
+            kind,
+            rot_at: 0,
+        };
+        let mut state = GameState {
+            size: 3,
+            crabs: vec![Crab {
+                name: "player".to_string(),
+                token,
+                hue: 0.0,
+                point: 0,
+                direction: Direction::E,
+                position: Position::new(0, 0),
+                npc: false,
+                stunned_until: 0,
+                energy: None,
+                energy_at: 0,
+                stage: 0,
+                molt_progress: 0,
+                team: None,
+            }],
+            foods: vec![food(1, FoodKind::Golden), food(2, FoodKind::Poison)],
+            paints: vec![],
+            ..GameState::new(3)
+        };
+        // ふつうのルールでは観戦画面にもごはんの種類を送らない
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["foods"][0].get("kind"), None);
+        state.config.food.reveal_kind = true;
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["foods"][0]["kind"], "Golden");
+        let scan = Command::PlayerCommand(PlayerCommand::Scan(ScanParam { token }));
+
+        // 上級者向けルールでは Scan でごはんの種類がわかる
+        assert_eq!(
+            state.proc_command(&scan).result,
+            CommandResult::Scan(ScanResult {
+                what_you_can_see: WhatYouCanSee::Food,
+                food_kind: Some(FoodKind::Golden),
+                team: None,
+            })
+        );
+
+        // 北を向いて右に歩き、金のごはん、毒のごはんの順に食べる
+        state.proc_command(&Command::PlayerCommand(PlayerCommand::Turn(TurnParam {
+            token,
+            side: Side::Left,
+        })));
+        let walk = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token,
+            side: Side::Right,
+        }));
+        let response = state.proc_command(&walk);
+        assert_eq!(
+            response.result,
+            CommandResult::Walk(WalkResult {
+                success: true,
+                point: 6,
+                total_point: 6,
+                energy: None,
+            })
+        );
+        let response = state.proc_command(&walk);
+        assert_eq!(
+            response.result,
+            CommandResult::Walk(WalkResult {
+                success: true,
+                point: -2,
+                total_point: 4,
+                energy: None,
+            })
+        );
+
+        // 動けなくなる毒では、効き目が切れるまで歩けない
+        state.config.food.poison_effect = PoisonEffect::Stun;
+        state.foods.push(food(1, FoodKind::Poison));
+        let turn = Command::PlayerCommand(PlayerCommand::Turn(TurnParam {
+            token,
+            side: Side::Left,
+        }));
+        state.proc_command(&turn);
+        state.proc_command(&turn);
+        let stun_wait = state.config.food.stun_wait;
+        assert!(state.proc_command(&walk).wait >= stun_wait);
+        let response = state.proc_command(&walk);
+        assert_eq!(response.result, CommandResult::CommandNotAllowed);
+        assert_eq!(response.wait, stun_wait);
+        assert_eq!(state.crabs[0].position, Position::new(1, 0));
+        state.now += stun_wait;
+        assert!(matches!(
+            state.proc_command(&walk).result,
+            CommandResult::Walk(WalkResult { success: true, .. })
+        ));
+        assert_eq!(state.crabs[0].position, Position::new(0, 0));
+    }
+
+    #[test]
+    fn test_team_play() {
+        use crate::game_config::TeamConfig;
+        let mut state = GameState::with_seed(3, 0).with_config(GameConfig {
+            team: TeamConfig {
+                scoring: TeamScoring::Pooled,
+            },
+            ..GameConfig::default()
+        });
+        let a = spawn(
+            &mut state,
+            SpawnParam {
+                team: Some("red".to_string()),
+                ..SpawnParam::new("a", 10.0)
+            },
+        );
+        let b = spawn(
+            &mut state,
+            SpawnParam {
+                team: Some("red".to_string()),
+                ..SpawnParam::new("b", 200.0)
+            },
+        );
+        let _ = spawn(&mut state, SpawnParam::new("c", 100.0));
+        //  +----+----+----+
+        //  | a  | 🍙 |    |  <- a (facing N)
+        //  +----+----+----+
+        //  |    | b  |    |  <- b (facing E)
+        //  +----+----+----+
+        //  |    |    | c  |
+        //  +----+----+----+
+        let layout = [
+            (0, 0, Direction::N),
+            (1, 1, Direction::E),
+            (2, 2, Direction::N),
+        ];
+        for (crab, (x, y, direction)) in state.crabs.iter_mut().zip(layout) {
+            crab.position = Position::new(x, y);
+            crab.direction = direction;
+        }
+        state.foods = vec![Food {
+            id: Token::new(),
+            position: Position::new(1, 0),
+            size: 2,
+            kind: FoodKind::Normal,
+            rot_at: 0,
+        }];
+        // チームの色相は最初に入ったカニの色相
+        assert_eq!(state.crabs[1].hue, 10.0);
+
+        let walk =
+            |token, side| Command::PlayerCommand(PlayerCommand::Walk(WalkParam { token, side }));
+        let paint = |token| Command::PlayerCommand(PlayerCommand::Paint(PaintParam { token }));
+        // a が食べたポイントは b とも共有する
+        let _ = state.proc_command(&walk(a, Side::Right));
+        assert_eq!(state.crabs[1].point, 2);
+        let _ = state.proc_command(&paint(a));
+        assert_eq!(state.crabs[1].point, 1);
+        let _ = state.proc_command(&walk(a, Side::Right));
+
+        // チームメイトが塗ったマスは塗り替えられない
+        let _ = state.proc_command(&walk(b, Side::Left));
+        assert_eq!(state.crabs[1].position, Position::new(1, 0));
+        let response = state.proc_command(&paint(b));
+        assert!(matches!(
+            response.result,
+            CommandResult::Paint(PaintResult {
+                success: false,
+                total_point: 1,
+                ..
+            })
+        ));
+        assert_eq!(state.paints[0].crab_token, a);
+
+        let leaderboard = state.team_leaderboard();
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].members, vec!["a", "b"]);
+        assert_eq!((leaderboard[0].point, leaderboard[0].paints), (1, 1));
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["crabs"][0]["team"], "red");
+    }
+
+    #[test]
+    fn test_capture_the_flag() {
+        let mut state = GameState::with_seed(7, 0).with_config(GameConfig {
+            mode: GameMode::CaptureTheFlag { capture_point: 10 },
+            ..GameConfig::default()
+        });
+        let token = spawn(
+            &mut state,
+            SpawnParam {
+                team: Some("red".to_string()),
+                ..SpawnParam::new("red", 0.0)
+            },
+        );
+        let blue = spawn(
+            &mut state,
+            SpawnParam {
+                team: Some("blue".to_string()),
+                ..SpawnParam::new("blue", 0.0)
+            },
+        );
+        // 陣地は四隅から順に置かれる。赤の陣地は (1, 1) のまわり、青の陣地は (5, 5) のまわり
+        //  +---+---+---+---+---+---+---+
+        //  |   |   |   |   |   |   | b |
+        //  +---+---+---+---+---+---+---+
+        //  |   |🚩 |   |   |   |   |   |
+        //  +---+---+---+---+---+---+---+
+        //  ...
+        //  +---+---+---+---+---+---+---+
+        //  |   |   |   | r |   |🚩 |   |  <- red (facing E)
+        //  +---+---+---+---+---+---+---+
+        state.crabs[0].position = Position::new(3, 5);
+        state.crabs[0].direction = Direction::E;
+        state.crabs[1].position = Position::new(6, 0);
+        state.foods.clear();
+        let scan = Command::PlayerCommand(PlayerCommand::Scan(ScanParam { token }));
+        let turn = |side| Command::PlayerCommand(PlayerCommand::Turn(TurnParam { token, side }));
+        let walk = |side| Command::PlayerCommand(PlayerCommand::Walk(WalkParam { token, side }));
+        let tick = Command::GameCycleCommand(GameCycleCommand::Tick);
+
+        // 陣地は見通せるので、陣地の中の旗が見える
+        let response = state.proc_command(&scan);
+        assert_eq!(
+            response.result,
+            CommandResult::Scan(ScanResult {
+                what_you_can_see: WhatYouCanSee::Flag,
+                food_kind: None,
+                team: Some("blue".to_string()),
+            })
+        );
+        // 相手の旗の上を歩くと拾う
+        let _ = state.proc_command(&turn(Side::Left));
+        let _ = state.proc_command(&walk(Side::Right));
+        let _ = state.proc_command(&walk(Side::Right));
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["captureTheFlag"]["flags"][1]["carriedBy"], "red");
+
+        for _ in 0..3 {
+            let _ = state.proc_command(&walk(Side::Left));
+        }
+        // 旗を持ったカニが押し出されると、旗も一緒に動く
+        state.config.crab.shove = true;
+        state.crabs[1].position = Position::new(3, 5);
+        state.crabs[1].direction = Direction::N;
+        let shove = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token: blue,
+            side: Side::Left,
+        }));
+        let _ = state.proc_command(&shove);
+        assert_eq!(state.crabs[0].position, Position::new(1, 5));
+        assert!(state.events().contains(&GameEvent::CrabMoved {
+            name: "red".to_string(),
+            from: Position::new(2, 5),
+            to: Position::new(1, 5),
+        }));
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["captureTheFlag"]["flags"][1]["position"]["x"], 1);
+        state.crabs[1].position = Position::new(6, 0);
+        let _ = state.proc_command(&walk(Side::Right));
+        // 北を向くと、ほかに何もなければ自分の陣地が見える
+        let response = state.proc_command(&scan);
+        assert!(matches!(
+            response.result,
+            CommandResult::Scan(ScanResult {
+                what_you_can_see: WhatYouCanSee::Base,
+                ..
+            })
+        ));
+        // 陣地に着くまでは得点にならない。旗はカニについてくる
+        let _ = state.proc_command(&turn(Side::Left));
+        let _ = state.proc_command(&walk(Side::Right));
+        let _ = state.proc_command(&tick);
+        assert_eq!(state.crabs[0].point, 0);
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["captureTheFlag"]["flags"][1]["position"]["y"], 4);
+
+        // 自分の陣地に持ち帰ると、game_cycle の Tick で得点になり旗は陣地に戻る
+        let _ = state.proc_command(&walk(Side::Right));
+        let _ = state.proc_command(&walk(Side::Right));
+        assert_eq!(state.crabs[0].position, Position::new(2, 2));
+        let response = state.proc_command(&tick);
+        assert!(response.mutated);
+        assert_eq!(state.crabs[0].point, 10);
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["captureTheFlag"]["bases"][0]["captures"], 1);
+        assert_eq!(json["captureTheFlag"]["flags"][1]["position"]["x"], 5);
+        assert!(json["captureTheFlag"]["flags"][1]["carriedBy"].is_null());
+
+        // 四隅が埋まると、7x7 のフィールドには 5 チーム目の陣地を置く場所がないので出現できない
+        for team in ["green", "yellow", "purple"] {
+            let command = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam {
+                team: Some(team.to_string()),
+                ..SpawnParam::new(team, 0.0)
+            }));
+            let response = state.proc_command(&command);
+            match team {
+                "purple" => assert_eq!(response.result, CommandResult::CommandNotAllowed),
+                _ => assert!(matches!(response.result, CommandResult::Spawn(_))),
+            }
+        }
+        assert_eq!(state.crab_count(), 4);
+    }
+
+    #[test]
+    fn test_king_of_the_hill() {
+        let mut state = GameState::with_seed(7, 0).with_config(GameConfig {
+            mode: GameMode::KingOfTheHill {
+                zones: 1,
+                move_interval: 10_000,
+                point_per_tick: 1,
+            },
+            ..GameConfig::default()
+        });
+        let token = spawn(&mut state, SpawnParam::new("a", 0.0));
+        let _ = spawn(&mut state, SpawnParam::new("b", 0.0));
+        let tick = Command::GameCycleCommand(GameCycleCommand::Tick);
+        // 最初の Tick で丘が現れる。ここでは丘を (3, 3) のまわりに置き直す
+        let _ = state.proc_command(&tick);
+        let center = Position::new(3, 3);
+        state.king_of_the_hill.as_mut().unwrap().hills[0].center = center;
+        state.foods.clear();
+
+        // 丘の外から丘の方を向くと、丘が見える
+        state.crabs[0].position = Position::new(3, 6);
+        state.crabs[0].direction = Direction::N;
+        state.crabs[1].position = Position::new(6, 6);
+        let scan = Command::PlayerCommand(PlayerCommand::Scan(ScanParam { token }));
+        assert_eq!(
+            state.proc_command(&scan).result,
+            CommandResult::Scan(ScanResult {
+                what_you_can_see: WhatYouCanSee::Hill,
+                food_kind: None,
+                team: None,
+            })
+        );
+
+        // 丘に 1 匹だけいれば Tick ごとにポイントがもらえる
+        state.crabs[0].position = center;
+        let response = state.proc_command(&tick);
+        assert!(response.mutated);
+        assert_eq!(state.crabs[0].point, 1);
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["kingOfTheHill"]["hills"][0]["holder"], "a");
+
+        // 取り合いになっている丘では誰ももらえない
+        state.crabs[1].position = Position::new(center.x + 1, center.y);
+        let _ = state.proc_command(&tick);
+        assert_eq!((state.crabs[0].point, state.crabs[1].point), (1, 0));
+        assert!(state.king_of_the_hill.as_ref().unwrap().hills[0].contested);
+
+        // ポイントを共有するチームでは、同じ Tick で 2 匹とももらえば 2 匹分増える
+        state.config.team.scoring = TeamScoring::Pooled;
+        for crab in &mut state.crabs {
+            crab.team = Some("red".to_string());
+            crab.point = 1;
+        }
+        let _ = state.proc_command(&tick);
+        assert_eq!((state.crabs[0].point, state.crabs[1].point), (3, 3));
+
+        // しばらくすると丘が動く (同じ場所に現れることもある)
+        state.set_now(10_000);
+        let _ = state.proc_command(&tick);
+        assert_eq!(state.king_of_the_hill.as_ref().unwrap().hills.len(), 1);
+    }
+
+    #[test]
+    fn test_paint_battle() {
+        let mut state = GameState::with_seed(3, 0).with_config(GameConfig {
+            mode: GameMode::PaintBattle {
+                match_time: 10_000,
+                overwrite_cost: 3,
+            },
+            ..GameConfig::default()
+        });
+        let a = spawn(&mut state, SpawnParam::new("a", 0.0));
+        let b = spawn(&mut state, SpawnParam::new("b", 0.0));
+        for crab in &mut state.crabs {
+            crab.point = 10;
+        }
+        // a と b が交互に (0, 0) に来て塗る
+        let paint_at_origin = |state: &mut GameState, token| {
+            for crab in &mut state.crabs {
+                crab.position = if crab.token == token {
+                    Position::new(0, 0)
+                } else {
+                    Position::new(2, 2)
+                };
+            }
+            let command = Command::PlayerCommand(PlayerCommand::Paint(PaintParam { token }));
+            match state.proc_command(&command).result {
+                CommandResult::Paint(result) => (result.success, result.total_point),
+                result => panic!("unexpected result: {:?}", result),
+            }
+        };
+        let tick = Command::GameCycleCommand(GameCycleCommand::Tick);
+
+        // 試合は最初の Tick で始まる
+        assert_eq!(paint_at_origin(&mut state, a), (false, 10));
+        let _ = state.proc_command(&tick);
+        // 何も塗られていないマスは 1、相手のマスは 3 ポイント
+        assert_eq!(paint_at_origin(&mut state, a), (true, 9));
+        assert_eq!(paint_at_origin(&mut state, b), (true, 7));
+        assert_eq!(paint_at_origin(&mut state, a), (true, 6));
+        // 重ね塗りしたマスは守られ、相手に塗り替えられない
+        assert_eq!(paint_at_origin(&mut state, a), (true, 5));
+        assert!(state.paints[0].protected);
+        assert_eq!(paint_at_origin(&mut state, b), (false, 7));
+        // 統計にも実際に使ったポイントが残る
+        assert_eq!(state.crab_stats("a").unwrap().points_spent_on_paint, 5);
+        assert_eq!(state.crab_stats("b").unwrap().points_spent_on_paint, 3);
+
+        // 試合が終わると集計が残り、もう塗れない
+        state.set_now(10_000);
+        let response = state.proc_command(&tick);
+        assert!(response.mutated);
+        assert_eq!(paint_at_origin(&mut state, b), (false, 7));
+        let tally = state.paint_tally();
+        assert_eq!(
+            (tally.crabs[0].name.as_str(), tally.crabs[0].cells),
+            ("a", 1)
+        );
+        assert_eq!(tally.painted, 1);
+        assert!((tally.coverage - 100.0 / 9.0).abs() < 1e-9);
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["paintBattle"]["remaining"], 0);
+
+        // 進行役の合図で、ペイントを消して試合をやり直す
+        let restart = Command::GameCycleCommand(GameCycleCommand::StartPaintBattle);
+        let _ = state.proc_command(&restart);
+        assert_eq!(state.paint_count(), 0);
+        assert_eq!(paint_at_origin(&mut state, b), (true, 6));
+    }
+
+    #[test]
+    fn test_stage() {
+        use crate::command::player_command::{StageProgress, StageStatusResult};
+        let mut state = GameState::with_seed(5, 0);
+        let spawn = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam {
+            name: "a".to_string(),
+            hue: 0.0,
+            team: None,
+            sandbox: None,
+        }));
+        let token = match state.proc_command(&spawn).result {
+            CommandResult::Spawn(result) => result.token,
+            result => panic!("unexpected result: {:?}", result),
+        };
+        let mut send =
+            |command: PlayerCommand| state.proc_command(&Command::PlayerCommand(command)).result;
+        let start = |stage: &str| {
+            PlayerCommand::StartStage(StartStageParam {
+                token,
+                stage: stage.to_string(),
+            })
+        };
+        let status = |steps, progress, hint: Option<&str>| {
+            CommandResult::StageStatus(StageStatusResult {
+                stage: "behind-the-rock".to_string(),
+                progress,
+                steps,
+                step_budget: 12,
+                hint: hint.map(str::to_string),
+            })
+        };
+        let turn = |side| PlayerCommand::Turn(TurnParam { token, side });
+        let walk = |side| PlayerCommand::Walk(WalkParam { token, side });
+
+        assert_eq!(send(start("no-such-stage")), CommandResult::StageNotFound);
+        assert_eq!(
+            send(start("behind-the-rock")),
+            status(0, StageProgress::Playing, None)
+        );
+        // ステージで使えないコマンドは数えない
+        assert_eq!(
+            send(PlayerCommand::Pinch(PinchParam { token })),
+            CommandResult::CommandNotAllowed
+        );
+
+        //  +----+----+----+----+----+
+        //  |    |    | 🍙 |    |    |
+        //  +----+----+----+----+----+
+        //  |    |    | 🪨 |    |    |
+        //  +----+----+----+----+----+
+        //  |    |    |    |    |    |
+        //  +----+----+----+----+----+
+        //  |    |    | a  |    |    |  <- a (facing E)
+        //  +----+----+----+----+----+
+        // 北を向くと、岩にさえぎられてごはんは見えない
+        send(turn(Side::Left));
+        assert_eq!(
+            send(PlayerCommand::Scan(ScanParam { token })),
+            CommandResult::Scan(ScanResult {
+                what_you_can_see: WhatYouCanSee::Wall,
+                food_kind: None,
+                team: None,
+            })
+        );
+        // 岩の横の列を進んで、ごはんの横で元の列に戻る
+        send(walk(Side::Left));
+        send(turn(Side::Right));
+        for _ in 0..3 {
+            send(walk(Side::Left));
+        }
+        send(turn(Side::Left));
+        let hint = "カニは左右にしか歩けません。Turn で向きを変えると進める方向も変わります";
+        assert_eq!(
+            send(PlayerCommand::StageStatus(StageStatusParam { token })),
+            status(8, StageProgress::Playing, Some(hint))
+        );
+        send(walk(Side::Right));
+        assert_eq!(
+            send(walk(Side::Right)),
+            status(9, StageProgress::Passed, None)
+        );
+
+        // Spawn し直すとフィールドに戻る
+        assert_eq!(state.crab_count(), 0);
+        state.proc_command(&spawn);
+        assert_eq!(state.crab_count(), 1);
+        assert!(state.stage_plays.is_empty());
+    }
+
+    #[test]
+    fn test_sandbox() {
+        use crate::command::player_command::{GraduateParam, ResetSandboxParam, SandboxMode};
+        let mut state = GameState::with_seed(5, 0);
+        let spawn = |mode| {
+            Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam {
+                sandbox: Some(mode),
+                ..SpawnParam::new("a", 0.0)
+            }))
+        };
+        let token = match state.proc_command(&spawn(SandboxMode::Fast)).result {
+            CommandResult::Spawn(result) => result.token,
+            result => panic!("unexpected result: {:?}", result),
+        };
+        let turn = Command::PlayerCommand(PlayerCommand::Turn(TurnParam {
+            token,
+            side: Side::Left,
+        }));
+        // サンドボックスのカニはフィールドにはいないが、同じトークンで操作でき、待たされない
+        assert_eq!(state.crab_count(), 0);
+        let response = state.proc_command(&turn);
+        assert_eq!(response.result, CommandResult::Turn);
+        assert_eq!((response.wait, response.mutated), (0, false));
+        // フィールドにごはんを出すときは、サンドボックスにも出す
+        state.proc_command(&Command::GameCycleCommand(GameCycleCommand::SpawnFood));
+        assert!(state.sandbox_state(&token).unwrap().food_count() > 0);
+
+        let reset =
+            Command::PlayerCommand(PlayerCommand::ResetSandbox(ResetSandboxParam { token }));
+        state.proc_command(&reset);
+        assert_eq!(state.sandbox_state(&token).unwrap().food_count(), 0);
+
+        // 卒業すると、同じトークンのままフィールドに出る
+        let graduate = Command::PlayerCommand(PlayerCommand::Graduate(GraduateParam { token }));
+        assert_eq!(
+            state.proc_command(&graduate).result,
+            CommandResult::Spawn(SpawnResult { token })
+        );
+        assert_eq!(state.crab_count(), 1);
+        assert!(state.sandbox_state(&token).is_none());
+        assert_eq!(state.proc_command(&turn).wait, 100);
+        assert_eq!(
+            state.proc_command(&reset).result,
+            CommandResult::SandboxNotFound
+        );
+
+        // Step モードでは 1 コマンドずつ待たせる
+        let token = match state.proc_command(&spawn(SandboxMode::Step)).result {
+            CommandResult::Spawn(result) => result.token,
+            result => panic!("unexpected result: {:?}", result),
+        };
+        assert_eq!(state.crab_count(), 0);
+        let scan = Command::PlayerCommand(PlayerCommand::Scan(ScanParam { token }));
+        assert_eq!(state.proc_command(&scan).wait, 1000);
+    }
+
+    #[test]
+    fn test_camera() {
+        let mut state = GameState::with_seed(20, 0);
+        let _ = spawn(&mut state, SpawnParam::new("a", 0.0));
+        let b = spawn(&mut state, SpawnParam::new("b", 0.0));
+        let layout = [(1, 1, Direction::N), (15, 10, Direction::N)];
+        for (crab, (x, y, direction)) in state.crabs.iter_mut().zip(layout) {
+            crab.position = Position::new(x, y);
+            crab.direction = direction;
+        }
+        state.foods = [(2, 1), (16, 10)]
+            .into_iter()
+            .map(|(x, y)| Food {
+                id: Token::new(),
+                position: Position::new(x, y),
+                size: 1,
+                kind: FoodKind::Normal,
+                rot_at: 0,
+            })
+            .collect();
+
+        // 範囲はフィールドからはみ出さないようにずらす
+        let view = state.view(&Camera::Follow("a".to_string()), 2).unwrap();
+        assert_eq!(
+            view.viewport,
+            Viewport {
+                left: 0,
+                top: 0,
+                size: 5
+            }
+        );
+        assert_eq!(view.state.crabs.len(), 1);
+        assert_eq!(view.state.foods.len(), 1);
+        assert!(state.view(&Camera::Follow("x".to_string()), 2).is_none());
+
+        // まだ誰もポイントを取っていなければ、一番ポイントの多いカニを映す
+        state.crabs[0].point = 3;
+        assert_eq!(state.view(&Camera::Highlights, 2).unwrap().following, "a");
+        // ポイントを取ったカニがハイライトになる
+        state.proc_command(&Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token: b,
+            side: Side::Right,
+        })));
+        let view = state.view(&Camera::Highlights, 2).unwrap();
+        assert_eq!(view.following, "b");
+        assert_eq!(view.viewport.left, 14);
+        // カメラには映る範囲で起きたことだけを送る
+        assert_eq!(state.view_events(&Camera::Highlights, 2).len(), 2);
+        assert!(state
+            .view_events(&Camera::Follow("a".to_string()), 2)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_perception() {
+        let mut state = GameState::with_seed(5, 0);
+        let command = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam::new("a", 0.0)));
+        let token = match state.proc_command(&command).result {
+            CommandResult::Spawn(result) => result.token,
+            result => panic!("unexpected result: {:?}", result),
+        };
+        state.crabs[0].point = 1;
+        state.proc_command(&Command::PlayerCommand(PlayerCommand::Paint(PaintParam {
+            token,
+        })));
+        let scan = Command::PlayerCommand(PlayerCommand::Scan(ScanParam { token }));
+        let result = state.proc_command(&scan).result;
+
+        let perception = state.perception(&token, Some(&result)).unwrap();
+        assert_eq!(perception.name, "a");
+        assert_eq!(perception.paints, vec![state.crabs[0].position]);
+        assert!(perception.scan.is_some());
+        // Scan 以外のコマンドのあとは Scan の結果を含めない
+        assert!(state.perception(&token, None).unwrap().scan.is_none());
+        assert!(state.perception(&Token::new(), None).is_none());
+    }
+
+    #[test]
+    fn test_events() {
+        let token = Token::new();
+        let other = Token::new();
+        //  +----+----+----+
+        //  | 🦀 | 🍙 | 🎨 |  <- player (facing N), food and other's paint
+        //  +----+----+----+
+        //  |    |    |    |
+        //  +----+----+----+
+        //  |    |    | 🦀 |  <- other
+        //  +----+----+----+
+        let crab = |name: &str, token, position| Crab {
+            name: name.to_string(),
+            token,
+            hue: 0.0,
+            point: 0,
+            direction: Direction::N,
+            position,
+            npc: false,
+            stunned_until: 0,
+            energy: None,
+            energy_at: 0,
+            stage: 0,
+            molt_progress: 0,
+            team: None,
+        };
+        let mut state = GameState {
+            size: 3,
+            crabs: vec![
+                crab("player", token, Position::new(0, 0)),
+                crab("other", other, Position::new(2, 2)),
+            ],
+            foods: vec![Food {
+                id: Token::new(),
+                position: Position::new(1, 0),
+                size: 2,
+                kind: FoodKind::Normal,
+                rot_at: 0,
+            }],
+            paints: vec![Paint::new(Position::new(2, 0), other, 0.0)],
+            ..GameState::new(3)
+        };
+        let walk = |side| Command::PlayerCommand(PlayerCommand::Walk(WalkParam { token, side }));
+
+        state.proc_command(&walk(Side::Left));
+        assert_eq!(
+            state.events(),
+            [GameEvent::WalkBlocked {
+                name: "player".to_string(),
+                by: WalkBlocked::Wall,
+            }]
+        );
+        state.proc_command(&walk(Side::Right));
+        assert_eq!(
+            state.events(),
+            [
+                GameEvent::CrabMoved {
+                    name: "player".to_string(),
+                    from: Position::new(0, 0),
+                    to: Position::new(1, 0),
+                },
+                GameEvent::FoodEaten {
+                    name: "player".to_string(),
+                    position: Position::new(1, 0),
+                    size: 2,
+                },
+            ]
+        );
+        state.proc_command(&walk(Side::Right));
+        state.proc_command(&Command::PlayerCommand(PlayerCommand::Paint(PaintParam {
+            token,
+        })));
+        assert_eq!(
+            state.events(),
+            [GameEvent::PaintOverwritten {
+                name: "player".to_string(),
+                position: Position::new(2, 0),
+                previous: "other".to_string(),
+            }]
+        );
+
+        // Spawn し直すと、いなくなってから出てくる
+        let spawn = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam::new("other", 0.0)));
+        state.proc_command(&spawn);
+        assert_eq!(state.events().len(), 2);
+        assert_eq!(
+            state.events()[0],
+            GameEvent::CrabRemoved {
+                name: "other".to_string()
+            }
+        );
+        assert!(matches!(state.events()[1], GameEvent::CrabSpawned { .. }));
+    }
+
+    #[test]
+    fn test_wait_for() {
+        let mut state = GameState::with_seed(5, 0);
+        let command = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam::new("a", 0.0)));
+        let token = match state.proc_command(&command).result {
+            CommandResult::Spawn(result) => result.token,
+            result => panic!("unexpected result: {:?}", result),
+        };
+        let param = WaitForParam {
+            token,
+            timeout: Some(3000),
+        };
+        // GameState だけで処理すると待たずに返る
+        let wait_for = Command::PlayerCommand(PlayerCommand::WaitFor(param.clone()));
+        let res = state.proc_command(&wait_for);
+        assert!(matches!(
+            res.result,
+            CommandResult::WaitFor(WaitForResult {
+                reason: WaitReason::Timeout,
+                ..
+            })
+        ));
+
+        let watch = state.watch(&param).unwrap();
+        state.proc_command(&Command::GameCycleCommand(GameCycleCommand::Tick));
+        assert!(watch.check(&state, 1000).is_none());
+        state.proc_command(&Command::GameCycleCommand(GameCycleCommand::SpawnFood));
+        let res = watch.check(&state, 2000).unwrap();
+        assert!(matches!(
+            res.result,
+            CommandResult::WaitFor(WaitForResult {
+                reason: WaitReason::FoodSpawned,
+                ..
+            })
+        ));
+        // 何も起きなければタイムアウトする
+        state.proc_command(&Command::GameCycleCommand(GameCycleCommand::Tick));
+        let watch = state.watch(&param).unwrap();
+        assert!(watch.check(&state, 2999).is_none());
+        let res = watch.check(&state, 3000).unwrap();
+        assert!(matches!(
+            res.result,
+            CommandResult::WaitFor(WaitForResult {
+                reason: WaitReason::Timeout,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_pinch_and_shove() {
+        let token = Token::new();
+        let other = Token::new();
+        //  +----+----+----+
+        //  | 🦀 | 🦀 |    |  <- player (facing E) and other
+        //  +----+----+----+
+        let crab = |name: &str, token, x| Crab {
+            name: name.to_string(),
+            token,
+            hue: 0.0,
+            point: 2,
+            direction: Direction::E,
+            position: Position::new(x, 0),
+            npc: false,
+            stunned_until: 0,
+            energy: None,
+            energy_at: 0,
+            stage: 0,
+            molt_progress: 0,
+            team: None,
+        };
+        let mut state = GameState {
+            size: 3,
+            crabs: vec![crab("player", token, 0), crab("other", other, 1)],
+            foods: vec![],
+            paints: vec![],
+            ..GameState::new(3)
+        };
+        let pinch = Command::PlayerCommand(PlayerCommand::Pinch(PinchParam { token }));
+
+        // 初心者向けのルールでははさめない
+        assert_eq!(
+            state.proc_command(&pinch).result,
+            CommandResult::Pinch(PinchResult {
+                success: false,
+                stolen: 0,
+                total_point: 2,
+            })
+        );
+
+        // ポイントを奪う
+        state.config.crab.pinch = PinchEffect::Steal;
+        assert_eq!(
+            state.proc_command(&pinch).result,
+            CommandResult::Pinch(PinchResult {
+                success: true,
+                stolen: 1,
+                total_point: 3,
             })
         );
+        assert_eq!(state.crabs[1].point, 1);
+
+        // はさまれたカニはしばらく結果が返らず、動くこともできない
+        state.config.crab.pinch = PinchEffect::Stun;
+        state.proc_command(&pinch);
+        let scan = Command::PlayerCommand(PlayerCommand::Scan(ScanParam { token: other }));
+        assert_eq!(state.proc_command(&scan).wait, 2000);
+        let stunned_walk = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token: other,
+            side: Side::Left,
+        }));
+        let response = state.proc_command(&stunned_walk);
+        assert_eq!(response.result, CommandResult::CommandNotAllowed);
+        assert_eq!(response.wait, 2000);
+        assert_eq!(state.crabs[1].position, Position::new(1, 0));
+
+        // 押し出せるルールでは、ぶつかったカニを押し出して進める
+        state.config.crab.shove = true;
+        state.crabs[0].direction = Direction::N;
+        let walk = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token,
+            side: Side::Right,
+        }));
+        let response = state.proc_command(&walk);
+        assert!(matches!(
+            response.result,
+            CommandResult::Walk(WalkResult { success: true, .. })
+        ));
+        assert_eq!(state.crabs[0].position, Position::new(1, 0));
+        assert_eq!(state.crabs[1].position, Position::new(2, 0));
+        // その先が壁なら押し出せない
+        let response = state.proc_command(&walk);
+        assert!(matches!(
+            response.result,
+            CommandResult::Walk(WalkResult { success: false, .. })
+        ));
+    }
+
+    #[test]
+    fn test_energy() {
+        let mut state = GameState::new(5);
+        state.config.energy.enabled = true;
+        state.config.energy.max = 5;
+        state.config.energy.regen_per_sec = 1;
+        let spawn = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam {
+            name: "player".to_string(),
+            hue: 0.0,
+            team: None,
+            sandbox: None,
+        }));
+        let CommandResult::Spawn(spawn) = state.proc_command(&spawn).result else {
+            panic!("spawn failed");
+        };
+        let paint = Command::PlayerCommand(PlayerCommand::Paint(PaintParam { token: spawn.token }));
+
+        // ポイントがなく失敗しても、エネルギーは使う
+        let CommandResult::Paint(result) = state.proc_command(&paint).result else {
+            panic!("paint failed");
+        };
+        assert_eq!(result.energy, Some(2));
+        state.proc_command(&paint);
+        // 0 になると行動できない
+        let response = state.proc_command(&paint);
+        assert_eq!(response.result, CommandResult::OutOfEnergy);
+        assert_eq!(response.wait, 1000);
+
+        // 時間が経つと回復する
+        state.set_now(1500);
+        let CommandResult::Paint(result) = state.proc_command(&paint).result else {
+            panic!("paint failed");
+        };
+        assert_eq!(result.energy, Some(0));
+        assert_eq!(
+            serde_json::to_value(&state).unwrap()["crabs"][0]["energy"],
+            0
+        );
+    }
+
+    #[test]
+    fn test_crab_molts_and_paints_wide() {
+        let token = Token::new();
+        //  +----+----+----+----+
+        //  |    |    |    |    |
+        //  +----+----+----+----+
+        //  | 🦀 | 🍙 | 🍙 |    |  <- player and foods
+        //  +----+----+----+----+
+        //  |    |    |    |    |
+        //  +----+----+----+----+
+        let food = |x| Food {
+            id: Token::new(),
+            position: Position::new(x, 1),
+            size: 1,
+            kind: FoodKind::Normal,
+            rot_at: 0,
+        };
+        let mut state = GameState {
+            size: 4,
+            crabs: vec![Crab {
+                name: "player".to_string(),
+                token,
+                hue: 0.0,
+                point: 0,
+                direction: Direction::N,
+                position: Position::new(0, 1),
+                npc: false,
+                stunned_until: 0,
+                energy: None,
+                energy_at: 0,
+                stage: 0,
+                molt_progress: 0,
+                team: None,
+            }],
+            foods: vec![food(1), food(2)],
+            paints: vec![],
+            ..GameState::new(4)
+        };
+        state.config.growth.enabled = true;
+        state.config.growth.foods_per_stage = 1;
+        let walk = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token,
+            side: Side::Right,
+        }));
+
+        // ごはんを食べるたびに脱皮し、大きくなると歩くのが遅くなる
+        assert_eq!(state.proc_command(&walk).wait, 500);
+        assert_eq!(state.crabs[0].stage, 1);
+        assert_eq!(state.proc_command(&walk).wait, 700);
+        assert_eq!(state.crabs[0].stage, 2);
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["crabs"][0]["stage"], 2);
+
+        // 3x3 のマスを一度に塗る
+        let paint = Command::PlayerCommand(PlayerCommand::Paint(PaintParam { token }));
+        let CommandResult::Paint(result) = state.proc_command(&paint).result else {
+            panic!("paint failed");
+        };
+        assert_eq!(result.your_paints.len(), 9);
+        assert_eq!(result.total_point, 1);
     }
 }
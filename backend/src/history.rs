@@ -0,0 +1,228 @@
+//! カニごとのコマンドの履歴
+//!
+//! ボットが思いどおりに動かないときに、サーバーがどんなコマンドを受け取ってどう処理したかを見られるように、
+//! カニごとに直近のコマンドと結果を残す。`GET /api/crabs/history?token=<token>` で古い順に返す。
+//! 霧の中モードでは `&key=<KANI_ADMIN_KEY>` を付けたときだけ返す
+
+use crate::admin::SocketAuth;
+use crate::command::player_command::PlayerCommand;
+use crate::command::{Command, CommandResponse, CommandResult};
+use crate::game_state::GameState;
+use crate::geometry::{Direction, Position};
+use crate::token::Token;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// カニ 1 匹あたりに残すコマンドの数
+const HISTORY_LEN: usize = 50;
+
+/// カニの位置と向き
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Pose {
+    pub position: Position,
+    pub direction: Direction,
+}
+
+/// 処理したコマンド 1 つ分
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HistoryEntry {
+    /// コマンドを処理した時刻 (ms)
+    pub at: u64,
+    pub command: PlayerCommand,
+    pub result: CommandResult,
+    /// 処理する前と後のカニの位置と向き。カニがいなければ None
+    pub before: Option<Pose>,
+    pub after: Option<Pose>,
+    /// 結果を返すまでに待たせた時間 (ms)
+    pub wait: u64,
+}
+
+#[derive(Debug)]
+struct CrabHistory {
+    /// カニ名。Spawn を見ていないトークンではトークンの文字列
+    name: String,
+    entries: VecDeque<HistoryEntry>,
+}
+
+/// カニごとの履歴。トークンごとに記録する
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    crabs: HashMap<Token, CrabHistory>,
+}
+
+/// `command` を送ったカニの、処理する前の位置と向き。command_processor でコマンドを処理する前に呼ぶ
+pub(crate) fn pose_before(command: &Command, state: &GameState) -> Option<Pose> {
+    let Command::PlayerCommand(command) = command else {
+        return None;
+    };
+    pose(state, &command.token()?)
+}
+
+fn pose(state: &GameState, token: &Token) -> Option<Pose> {
+    let (position, direction) = state.pose(token)?;
+    Some(Pose {
+        position,
+        direction,
+    })
+}
+
+impl History {
+    /// command_processor で処理したプレイヤーのコマンドを記録する。
+    /// でたらめなトークンで履歴が増え続けないように、カニがいないトークンは記録しない
+    pub(crate) fn record(
+        &mut self,
+        command: &Command,
+        before: Option<Pose>,
+        response: &CommandResponse,
+        now: u64,
+        state: &GameState,
+    ) {
+        let Command::PlayerCommand(command) = command else {
+            return;
+        };
+        let token = match (command, &response.result) {
+            (PlayerCommand::Spawn(param), CommandResult::Spawn(result)) => {
+                // Spawn し直したカニの古いトークンの履歴は消す
+                self.crabs.retain(|_, crab| crab.name != param.name);
+                self.crabs.insert(
+                    result.token,
+                    CrabHistory {
+                        name: param.name.clone(),
+                        entries: VecDeque::new(),
+                    },
+                );
+                result.token
+            }
+            _ => match command.token() {
+                Some(token) => token,
+                None => return,
+            },
+        };
+        let after = pose(state, &token);
+        if before.is_none() && after.is_none() && !self.crabs.contains_key(&token) {
+            return;
+        }
+        let crab = self.crabs.entry(token).or_insert_with(|| CrabHistory {
+            name: token.to_string(),
+            entries: VecDeque::new(),
+        });
+        if crab.entries.len() == HISTORY_LEN {
+            crab.entries.pop_front();
+        }
+        crab.entries.push_back(HistoryEntry {
+            at: now,
+            command: command.clone(),
+            result: response.result.clone(),
+            before,
+            after,
+            wait: response.wait,
+        });
+    }
+
+    /// `token` のカニの履歴。古い順
+    pub(crate) fn entries(&self, token: &Token) -> Option<Vec<HistoryEntry>> {
+        let crab = self.crabs.get(token)?;
+        Some(crab.entries.iter().cloned().collect())
+    }
+}
+
+#[derive(Clone)]
+struct HistoryState {
+    history: Arc<Mutex<History>>,
+    admin_key: Option<String>,
+    fog_of_war: bool,
+}
+
+pub(crate) fn router(
+    history: Arc<Mutex<History>>,
+    admin_key: Option<String>,
+    fog_of_war: bool,
+) -> Router {
+    Router::new()
+        .route("/api/crabs/history", get(get_history))
+        .with_state(HistoryState {
+            history,
+            admin_key,
+            fog_of_war,
+        })
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct HistoryQuery {
+    token: String,
+}
+
+async fn get_history(
+    State(state): State<HistoryState>,
+    Query(query): Query<HistoryQuery>,
+    Query(auth): Query<SocketAuth>,
+) -> Result<Json<Vec<HistoryEntry>>, StatusCode> {
+    if !auth.can_see_field(&state.admin_key, state.fog_of_war) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let token: Token = query.token.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let entries = state.history.lock().await.entries(&token);
+    entries.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::player_command::{SpawnParam, TurnParam};
+    use crate::geometry::Side;
+
+    #[test]
+    fn test_history() {
+        let mut state = GameState::with_seed(5, 0);
+        let mut history = History::default();
+        let spawn = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam::new("a", 0.0)));
+        let response = state.proc_command(&spawn);
+        history.record(&spawn, None, &response, 0, &state);
+        let CommandResult::Spawn(spawned) = response.result else {
+            panic!("unexpected result: {:?}", response.result);
+        };
+        let token = spawned.token;
+        let turn = Command::PlayerCommand(PlayerCommand::Turn(TurnParam {
+            token,
+            side: Side::Left,
+        }));
+        for i in 1..=HISTORY_LEN as u64 {
+            let before = pose_before(&turn, &state);
+            let response = state.proc_command(&turn);
+            history.record(&turn, before, &response, i * 1000, &state);
+        }
+
+        // 古いものから捨てる
+        let entries = history.entries(&token).unwrap();
+        assert_eq!(entries.len(), HISTORY_LEN);
+        assert_eq!(entries[0].at, 1000);
+        let last = entries.last().unwrap();
+        assert_eq!(last.result, CommandResult::Turn);
+        assert_eq!(
+            last.after.unwrap().direction,
+            last.before.unwrap().direction.turn(Side::Left)
+        );
+
+        // 知らないトークンは記録しない
+        let unknown = Token::new();
+        let turn = Command::PlayerCommand(PlayerCommand::Turn(TurnParam {
+            token: unknown,
+            side: Side::Left,
+        }));
+        let response = state.proc_command(&turn);
+        history.record(&turn, None, &response, 0, &state);
+        assert!(history.entries(&unknown).is_none());
+
+        // Spawn し直すと新しいトークンで記録しなおす
+        let response = state.proc_command(&spawn);
+        history.record(&spawn, None, &response, 0, &state);
+        assert!(history.entries(&token).is_none());
+    }
+}
@@ -0,0 +1,96 @@
+//! キング・オブ・ザ・ヒル (陣取り) モード
+//!
+//! フィールドに丘 (ゾーン) が現れ、しばらくすると別の場所に移る。
+//! game_cycle の Tick のたびに、丘にいるカニがポイントをもらえる。
+//! ほかのチーム (チームに入っていなければほかのカニ) と取り合っている丘では誰ももらえない
+
+use crate::crab::Crab;
+use crate::geometry::Position;
+use crate::token::Token;
+use rand::Rng;
+
+/// 丘。中心のまわり 3x3 マス
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Hill {
+    pub center: Position,
+    /// 丘を取っているチーム名 (チームに入っていなければカニ名)
+    pub holder: Option<String>,
+    /// 取り合いになっているか
+    pub contested: bool,
+}
+
+impl Hill {
+    pub(crate) fn contains(&self, position: &Position) -> bool {
+        (position.x - self.center.x).abs() <= 1 && (position.y - self.center.y).abs() <= 1
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KingOfTheHill {
+    pub hills: Vec<Hill>,
+    /// 丘を最後に動かした時刻 (ms)
+    #[serde(skip)]
+    moved_at: u64,
+}
+
+impl KingOfTheHill {
+    /// game_cycle ごとの処理。`move_interval` ごとに丘を動かし、
+    /// 丘を取っているカニのトークンを返す
+    pub(crate) fn tick(
+        &mut self,
+        crabs: &[Crab],
+        now: u64,
+        size: u32,
+        zones: usize,
+        move_interval: u64,
+        rng: &mut impl Rng,
+    ) -> Vec<Token> {
+        if self.hills.is_empty() || now >= self.moved_at + move_interval {
+            self.move_hills(size, zones, rng);
+            self.moved_at = now;
+        }
+        let mut holders = vec![];
+        for hill in &mut self.hills {
+            let inside: Vec<&Crab> = crabs
+                .iter()
+                .filter(|c| hill.contains(&c.position))
+                .collect();
+            let mut sides: Vec<&String> = inside
+                .iter()
+                .map(|c| c.team.as_ref().unwrap_or(&c.name))
+                .collect();
+            sides.sort();
+            sides.dedup();
+            hill.contested = sides.len() > 1;
+            hill.holder = match sides[..] {
+                [side] => Some(side.clone()),
+                _ => None,
+            };
+            if hill.holder.is_some() {
+                holders.extend(inside.iter().map(|c| c.token));
+            }
+        }
+        holders
+    }
+
+    /// 丘をランダムな位置に置き直す
+    fn move_hills(&mut self, size: u32, zones: usize, rng: &mut impl Rng) {
+        let far = (size as i32 - 2).max(1);
+        self.hills = (0..zones)
+            .map(|_| Hill {
+                center: Position::new(rng.gen_range(1..=far), rng.gen_range(1..=far)),
+                holder: None,
+                contested: false,
+            })
+            .collect();
+    }
+
+    /// `position` のマスを含む丘があるか。`from` (Scan したカニの位置) を含む丘は除く
+    pub(crate) fn hill_at(&self, position: &Position, from: &Position) -> bool {
+        self.hills
+            .iter()
+            .any(|h| h.contains(position) && !h.contains(from))
+    }
+}
@@ -0,0 +1,26 @@
+//! kani-life のゲームロジック
+//!
+//! サーバーのほか、シミュレーターからも使えるようにライブラリとして公開している
+
+pub mod camera;
+mod capture_the_flag;
+pub mod command;
+mod crab;
+pub mod event;
+mod food;
+pub mod food_policy;
+pub mod game_config;
+pub mod game_state;
+mod king_of_the_hill;
+mod paint;
+pub mod paint_battle;
+pub mod perception;
+mod sandbox;
+pub mod simulator;
+pub mod stage;
+pub mod stats;
+pub mod strategy;
+pub mod team;
+pub mod wait_for;
+
+pub use kani_life_protocol::{geometry, token};
@@ -1,27 +1,40 @@
-mod command;
-mod crab;
-mod food;
-mod game_state;
-mod geometry;
-mod paint;
+mod activity;
+mod admin;
+mod config;
+mod event_stream;
+mod history;
+mod metrics;
+mod npc;
+mod openapi;
+mod report;
+mod spectator;
 mod telemetry;
-mod token;
+mod wasm_bot;
 
 use crate::command::{
     game_cycle_command::GameCycleCommand, player_command::PlayerCommand, Command, CommandResponse,
 };
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 use axum::http::StatusCode;
-use axum::routing::post;
+use axum::response::Response;
+use axum::routing::{get, post};
 use axum::{
     extract::{Json, State},
     Router,
 };
+use kani_life::event::GameEvent;
+use kani_life::wait_for::Watch;
+use kani_life::{command, game_state, geometry, token};
+use socketioxide::extract::{SocketRef, TryData};
 use socketioxide::layer::SocketIoLayer;
-use socketioxide::{extract::SocketRef, SocketIo};
-use std::{sync::Arc, time::Duration};
+use socketioxide::SocketIo;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::{
-    sync::{mpsc, oneshot, Mutex},
-    time::sleep,
+    sync::{broadcast, mpsc, oneshot, Mutex},
+    time::{sleep, sleep_until},
 };
 use tower_http::services::ServeDir;
 
@@ -31,6 +44,8 @@ struct GameCommandCase {
     /// ゲームプロセッサがコマンド送信元に結果を返すためのセンダー
     callback_tx: oneshot::Sender<CommandResponse>,
     parent_span: tracing::Span,
+    /// キューに積まれた時刻
+    enqueued_at: Instant,
 }
 
 #[derive(Debug, Clone)]
@@ -41,45 +56,126 @@ struct CommanderState {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let subscriber = telemetry::init_tracing_subscriber("kani_life");
+    // ログの設定を読むときの警告は、まだ subscriber がないので標準の出力に出す
+    let telemetry_config = tracing::subscriber::with_default(
+        tracing_subscriber::fmt().finish(),
+        config::TelemetryConfig::from_env,
+    );
+    let subscriber = telemetry::init_tracing_subscriber("kani_life", &telemetry_config)?;
     tracing::subscriber::set_global_default(subscriber)?;
 
     let (command_tx, command_rx) = mpsc::channel::<GameCommandCase>(100);
 
-    let game_state = Arc::new(Mutex::new(game_state::GameState::new(30)));
+    let game_state = Arc::new(Mutex::new(
+        game_state::GameState::new(30).with_config(config::game_config_from_env()),
+    ));
 
     let commander_state = Arc::new(Mutex::new(CommanderState {
         tx: command_tx.clone(),
     }));
 
-    let (socket_layer, socket_io) = socket_layer(game_state.clone());
+    let metrics = Arc::new(metrics::Metrics::new()?);
+    let activity = Arc::new(Mutex::new(activity::Activity::default()));
+    let history = Arc::new(Mutex::new(history::History::default()));
+    // ゲーム内の時刻の基準
+    let started_at = Instant::now();
+    let events = event_stream::channel();
+
+    let (socket_layer, socket_io) = socket_layer(
+        game_state.clone(),
+        metrics.clone(),
+        config::admin_key_from_env(),
+        config::fog_of_war_from_env(),
+    );
+    activity::serve(
+        &socket_io,
+        activity.clone(),
+        config::admin_key_from_env(),
+        started_at,
+    );
 
-    command_processor(game_state, command_rx, socket_io);
+    command_processor(
+        game_state.clone(),
+        command_tx.clone(),
+        command_rx,
+        socket_io,
+        Recorders {
+            metrics: metrics.clone(),
+            activity,
+            history: history.clone(),
+        },
+        events.clone(),
+        started_at,
+    );
     game_cycle(command_tx.clone());
+    npc::spawn_npcs(&config::NpcConfig::from_env(), command_tx.clone());
 
     let app = Router::new()
         .route("/api/command", post(post_command))
+        .route("/api/ws", get(ws_command))
         .with_state(commander_state)
+        .merge(wasm_bot::router(command_tx.clone()))
+        .merge(openapi::router())
+        .merge(report::router(
+            game_state,
+            config::admin_key_from_env(),
+            config::fog_of_war_from_env(),
+        ))
+        .merge(history::router(
+            history,
+            config::admin_key_from_env(),
+            config::fog_of_war_from_env(),
+        ))
+        .merge(admin::router(
+            command_tx.clone(),
+            config::admin_key_from_env(),
+        ))
+        .merge(metrics::router(metrics, command_tx.clone()))
+        .merge(event_stream::router(
+            events,
+            config::admin_key_from_env(),
+            config::fog_of_war_from_env(),
+        ))
         .nest_service("/", ServeDir::new("static"))
         .layer(socket_layer);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
-    println!("Listening on: {}", listener.local_addr().unwrap());
+    tracing::info!("Listening on: {}", listener.local_addr().unwrap());
     axum::serve(listener, app).await.unwrap();
 
     Ok(())
 }
 
-fn socket_layer(game_state: Arc<Mutex<game_state::GameState>>) -> (SocketIoLayer, SocketIo) {
+fn socket_layer(
+    game_state: Arc<Mutex<game_state::GameState>>,
+    metrics: Arc<metrics::Metrics>,
+    admin_key: Option<String>,
+    fog_of_war: bool,
+) -> (SocketIoLayer, SocketIo) {
     let (layer, io) = SocketIo::new_layer();
 
-    io.ns("/", |s: SocketRef| {
-        s.on("get", |s: SocketRef| async move {
-            println!("Received get event");
-            let state = game_state.lock().await.clone();
-            s.emit("state", state).expect("TODO: panic message");
-        })
-    });
+    io.ns(
+        "/",
+        move |s: SocketRef, TryData(auth): TryData<admin::SocketAuth>| {
+            metrics.socket_connected();
+            let disconnected = metrics.clone();
+            s.on_disconnect(move || disconnected.socket_disconnected());
+            spectator::on_connect_player(&s, game_state.clone());
+            // 霧の中モードでは、フィールド全体は進行役のキーを持つ観戦者にしか見せない
+            if fog_of_war && !auth.is_ok_and(|auth| auth.is_admin(&admin_key)) {
+                return;
+            }
+            spectator::on_connect_spectator(&s, game_state.clone());
+            s.on("get", |s: SocketRef| async move {
+                tracing::debug!("Received get event");
+                let state = game_state.lock().await.clone();
+                if s.emit("state", state).is_err() {
+                    tracing::error!("Failed to emit state");
+                    metrics.emit_failed();
+                }
+            })
+        },
+    );
 
     (layer, io)
 }
@@ -93,50 +189,175 @@ async fn post_command(
     let root =
         tracing::span!(parent: None, tracing::Level::TRACE, "post_command", command = ?command);
     let _enter = root.enter();
-    println!("Posted command: {:?}", command);
-    let (response_tx, response_rx) = oneshot::channel::<CommandResponse>();
+    tracing::debug!("Posted command: {:?}", command);
     let command_tx = state.lock().await.tx.clone();
+    let command = Command::PlayerCommand(command);
+    let Some(response) = enqueue_command(&command_tx, command, root.clone()).await else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    tracing::debug!("Received response: {:?}", response);
+    if response.wait > 0 {
+        sleep(Duration::from_millis(response.wait)).await;
+    }
+    Ok(Json(response.result))
+}
+
+/// WebSocket をつなぎっぱなしにしてコマンドを受け付ける
+/// テキストメッセージ 1 つが 1 コマンドで、待ち時間が過ぎてから結果を返す
+async fn ws_command(
+    State(state): State<Arc<Mutex<CommanderState>>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let command_tx = state.lock().await.tx.clone();
+    ws.on_upgrade(move |socket| handle_ws_command(socket, command_tx))
+}
+
+async fn handle_ws_command(mut socket: WebSocket, command_tx: mpsc::Sender<GameCommandCase>) {
+    while let Some(Ok(message)) = socket.recv().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let command = match serde_json::from_str::<PlayerCommand>(&text) {
+            Ok(command) => command,
+            Err(e) => {
+                // 閉じてしまうと理由がわからないので、エラーを返してつなぎっぱなしにする
+                tracing::warn!("Failed to parse command: {}", e);
+                let error = serde_json::json!({ "error": format!("invalid command: {}", e) });
+                if socket.send(Message::Text(error.to_string())).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+        let root =
+            tracing::span!(parent: None, tracing::Level::TRACE, "ws_command", command = ?command);
+        let command = Command::PlayerCommand(command);
+        let Some(response) = enqueue_command(&command_tx, command, root).await else {
+            break;
+        };
+        if response.wait > 0 {
+            sleep(Duration::from_millis(response.wait)).await;
+        }
+        let Ok(text) = serde_json::to_string(&response.result) else {
+            break;
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// コマンドをキューに積み、処理結果を待つ
+async fn enqueue_command(
+    command_tx: &mpsc::Sender<GameCommandCase>,
+    command: Command,
+    parent_span: tracing::Span,
+) -> Option<CommandResponse> {
+    let (response_tx, response_rx) = oneshot::channel::<CommandResponse>();
     let send_result = command_tx
         .send(GameCommandCase {
-            command: Command::PlayerCommand(command),
+            command,
             callback_tx: response_tx,
-            parent_span: root.clone(),
+            parent_span,
+            enqueued_at: Instant::now(),
         })
         .await;
     if let Err(e) = send_result {
-        eprintln!("Failed to send command: {}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        tracing::error!("Failed to send command: {}", e);
+        return None;
     }
-    // rx で受け取った結果を返す
-    let response = response_rx.await;
-    match response {
-        Ok(response) => {
-            println!("Received response: {:?}", response);
-            if response.wait > 0 {
-                sleep(Duration::from_millis(response.wait)).await;
-            }
-            Ok(Json(response.result))
-        }
+    match response_rx.await {
+        Ok(response) => Some(response),
         Err(e) => {
-            eprintln!("Failed to receive response: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to receive response: {}", e);
+            None
+        }
+    }
+}
+
+/// command_processor で処理したコマンドを記録する先
+struct Recorders {
+    metrics: Arc<metrics::Metrics>,
+    activity: Arc<Mutex<activity::Activity>>,
+    history: Arc<Mutex<history::History>>,
+}
+
+/// 処理したが、まだ結果を返していないコマンド
+struct Pending {
+    command: Command,
+    callback_tx: oneshot::Sender<CommandResponse>,
+    /// キューに積まれてから取り出されるまでの時間。WaitFor で結果を待たせた時間は含めない
+    queue_wait: Duration,
+    /// 処理する前のカニの位置と向き
+    before: Option<history::Pose>,
+}
+
+impl Recorders {
+    /// 処理したコマンドを記録し、結果を送り返す
+    async fn respond(
+        &self,
+        io: &SocketIo,
+        state: &game_state::GameState,
+        pending: Pending,
+        response: CommandResponse,
+        now: u64,
+    ) {
+        let Pending {
+            command,
+            callback_tx,
+            queue_wait,
+            before,
+        } = pending;
+        self.metrics
+            .observe_command(&command, &response.result, queue_wait, state);
+        self.activity
+            .lock()
+            .await
+            .record(&command, before, &response.result, now, state);
+        self.history
+            .lock()
+            .await
+            .record(&command, before, &response, now, state);
+        // コマンドを送ったカニが知覚できることは、そのカニを購読しているプレイヤーに配信する
+        let token = match &command {
+            Command::PlayerCommand(command) => command.token(),
+            Command::GameCycleCommand(_) => None,
+        };
+        if let Some(token) = token {
+            if !spectator::perceive(io, state, &token, &response.result) {
+                tracing::error!("Failed to emit perception");
+                self.metrics.emit_failed();
+            }
         }
+        if callback_tx.send(response).is_err() {
+            tracing::warn!("Failed to send response");
+        };
     }
 }
 
 /// キュー (mpsc::channel) に積まれたコマンドを処理するループスレッド
+///
+/// WaitFor は処理しても結果を返さずに取っておき、ほかのコマンドを処理するたびに何か起きたか確かめる。
+/// タイムアウトの時刻には `command_tx` に Wake を送って、ほかにコマンドが来なくても確かめられるようにする
 fn command_processor(
     game_state: Arc<Mutex<game_state::GameState>>,
+    command_tx: mpsc::Sender<GameCommandCase>,
     mut command_rx: mpsc::Receiver<GameCommandCase>,
     io: SocketIo,
+    recorders: Recorders,
+    events: broadcast::Sender<GameEvent>,
+    started_at: Instant,
 ) {
     tokio::spawn(async move {
+        let mut waiting: Vec<(Pending, Watch)> = vec![];
         while let Some(GameCommandCase {
             command,
             callback_tx,
             parent_span,
+            enqueued_at,
         }) = command_rx.recv().await
         {
+            let queue_wait = enqueued_at.elapsed();
             let root = tracing::span!(
                 parent: &parent_span,
                 tracing::Level::TRACE,
@@ -144,51 +365,132 @@ fn command_processor(
             );
             let _enter = root.enter();
             let mut state = game_state.lock().await;
+            let now = started_at.elapsed().as_millis() as u64;
+            state.set_now(now);
+            let before = history::pose_before(&command, &state);
             let response = state.proc_command(&command);
             let mutated = response.mutated;
-            if callback_tx.send(response).is_err() {
-                eprintln!("Failed to send response");
+            // 待っている WaitFor のうち、このコマンドで何か起きたものには結果を返す
+            for (pending, watch) in std::mem::take(&mut waiting) {
+                match watch.check(&state, now) {
+                    Some(response) => recorders.respond(&io, &state, pending, response, now).await,
+                    None => waiting.push((pending, watch)),
+                }
+            }
+            let watch = match (&command, &response.result) {
+                (
+                    Command::PlayerCommand(PlayerCommand::WaitFor(param)),
+                    command::CommandResult::WaitFor(_),
+                ) => state.watch(param),
+                _ => None,
             };
-            if mutated && io.emit("state", state.clone()).is_err() {
-                eprintln!("Failed to emit state");
-                break;
+            let pending = Pending {
+                command,
+                callback_tx,
+                queue_wait,
+                before,
+            };
+            match watch {
+                Some(watch) => {
+                    wake_at(command_tx.clone(), started_at, &watch);
+                    waiting.push((pending, watch));
+                }
+                None => recorders.respond(&io, &state, pending, response, now).await,
+            }
+            if mutated && !spectator::broadcast(&io, &state) {
+                tracing::error!("Failed to emit state");
+                recorders.metrics.emit_failed();
+            }
+            // 起きたことは状態と一緒に配信する。SSE の購読者がいなければ送れないが問題ない
+            if !state.events().is_empty() {
+                if !spectator::broadcast_events(&io, &state) {
+                    tracing::error!("Failed to emit events");
+                    recorders.metrics.emit_failed();
+                }
+                for event in state.events() {
+                    events.send(event.clone()).ok();
+                }
             }
         }
     });
 }
 
+/// WaitFor がタイムアウトする時刻に、キューに Wake を積んで command_processor を起こす
+fn wake_at(command_tx: mpsc::Sender<GameCommandCase>, started_at: Instant, watch: &Watch) {
+    let at = started_at + Duration::from_millis(watch.deadline());
+    tokio::spawn(async move {
+        sleep_until(at.into()).await;
+        let root = tracing::span!(parent: None, tracing::Level::TRACE, "wake");
+        let command = Command::GameCycleCommand(GameCycleCommand::Wake);
+        enqueue_command(&command_tx, command, root).await;
+    });
+}
+
 /// 自動的に食べ物を生成するなどのゲームサイクルを処理するループスレッド
 fn game_cycle(command_tx: mpsc::Sender<GameCommandCase>) {
     // food loop
+    cycle_loop(command_tx.clone(), GameCycleCommand::SpawnFood);
+    // ゲームモードのルールを進める loop
+    cycle_loop(command_tx, GameCycleCommand::Tick);
+}
+
+/// コマンドを送り、返ってきた待ち時間だけ待つことを繰り返す
+fn cycle_loop(command_tx: mpsc::Sender<GameCommandCase>, command: GameCycleCommand) {
     tokio::spawn(async move {
         loop {
             let root = tracing::span!(parent:None, tracing::Level::TRACE, "game_cycle");
             let _enter = root.enter();
-            let command = Command::GameCycleCommand(GameCycleCommand::SpawnFood);
-            let (response_tx, response_rx) = oneshot::channel::<CommandResponse>();
-            let send_result = command_tx
-                .send(GameCommandCase {
-                    command,
-                    callback_tx: response_tx,
-                    parent_span: root.clone(),
-                })
-                .await;
-            if let Err(e) = send_result {
-                eprintln!("Failed to send command: {}", e);
+            let command = Command::GameCycleCommand(command.clone());
+            let Some(response) = enqueue_command(&command_tx, command, root.clone()).await else {
                 break;
-            }
-            let response = response_rx.await;
-            match response {
-                Ok(response) => {
-                    if response.wait > 0 {
-                        sleep(Duration::from_millis(response.wait)).await;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to receive response: {}", e);
-                    break;
-                }
+            };
+            if response.wait > 0 {
+                sleep(Duration::from_millis(response.wait)).await;
             }
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use command::player_command::{SpawnParam, WaitForParam, WaitForResult, WaitReason};
+
+    #[tokio::test]
+    async fn test_wait_for_times_out_without_other_commands() {
+        let mut state = game_state::GameState::with_seed(5, 0);
+        let spawn = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam::new("a", 0.0)));
+        let token = match state.proc_command(&spawn).result {
+            command::CommandResult::Spawn(result) => result.token,
+            result => panic!("unexpected result: {:?}", result),
+        };
+        let watch = state
+            .watch(&WaitForParam {
+                token,
+                timeout: Some(50),
+            })
+            .unwrap();
+
+        // ほかにコマンドが来なくても、タイムアウトの時刻に Wake が届く
+        let (command_tx, mut command_rx) = mpsc::channel(1);
+        let started_at = Instant::now();
+        wake_at(command_tx, started_at, &watch);
+        let case = command_rx.recv().await.unwrap();
+        assert!(matches!(
+            case.command,
+            Command::GameCycleCommand(GameCycleCommand::Wake)
+        ));
+        let now = started_at.elapsed().as_millis() as u64;
+        assert!(now >= 50);
+        state.set_now(now);
+        assert!(!state.proc_command(&case.command).mutated);
+        let response = watch.check(&state, now).unwrap();
+        assert!(matches!(
+            response.result,
+            command::CommandResult::WaitFor(WaitForResult {
+                reason: WaitReason::Timeout,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,199 @@
+//! Prometheus 形式でゲームとサーバーの状態を公開する
+
+use crate::command::{Command, CommandResult};
+use crate::game_state::GameState;
+use crate::GameCommandCase;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+pub(crate) struct Metrics {
+    registry: Registry,
+    /// コマンドの種類と結果ごとの処理数
+    commands: IntCounterVec,
+    /// キューに積まれているコマンド数 (取得するたびに計算する)
+    queue_depth: IntGauge,
+    /// コマンドがキューに積まれてから処理が始まるまでの時間
+    queue_wait: Histogram,
+    crabs: IntGauge,
+    foods: IntGauge,
+    paints: IntGauge,
+    socket_clients: IntGauge,
+    emit_failures: IntCounter,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("kani_life".to_string()), None)?;
+        let metrics = Metrics {
+            commands: IntCounterVec::new(
+                Opts::new("commands_total", "Processed commands"),
+                &["command", "result"],
+            )?,
+            queue_depth: IntGauge::new("command_queue_depth", "Commands waiting in the queue")?,
+            queue_wait: Histogram::with_opts(HistogramOpts::new(
+                "command_queue_wait_seconds",
+                "Time commands spent waiting in the queue",
+            ))?,
+            crabs: IntGauge::new("crabs", "Crabs on the field")?,
+            foods: IntGauge::new("foods", "Foods on the field")?,
+            paints: IntGauge::new("paints", "Painted cells on the field")?,
+            socket_clients: IntGauge::new("socket_clients", "Connected socket.io clients")?,
+            emit_failures: IntCounter::new("emit_failures_total", "Failed socket.io emits")?,
+            registry,
+        };
+        metrics
+            .registry
+            .register(Box::new(metrics.commands.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.queue_depth.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.queue_wait.clone()))?;
+        metrics.registry.register(Box::new(metrics.crabs.clone()))?;
+        metrics.registry.register(Box::new(metrics.foods.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.paints.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.socket_clients.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.emit_failures.clone()))?;
+        Ok(metrics)
+    }
+
+    /// command_processor で処理したコマンドを記録する
+    pub(crate) fn observe_command(
+        &self,
+        command: &Command,
+        result: &CommandResult,
+        queue_wait: std::time::Duration,
+        state: &GameState,
+    ) {
+        self.commands
+            .with_label_values(&[command_label(command), result_label(result)])
+            .inc();
+        self.queue_wait.observe(queue_wait.as_secs_f64());
+        self.crabs.set(state.crab_count() as i64);
+        self.foods.set(state.food_count() as i64);
+        self.paints.set(state.paint_count() as i64);
+    }
+
+    pub(crate) fn socket_connected(&self) {
+        self.socket_clients.inc();
+    }
+
+    pub(crate) fn socket_disconnected(&self) {
+        self.socket_clients.dec();
+    }
+
+    pub(crate) fn emit_failed(&self) {
+        self.emit_failures.inc();
+    }
+}
+
+fn command_label(command: &Command) -> &'static str {
+    match command {
+        Command::PlayerCommand(command) => command.name(),
+        Command::GameCycleCommand(_) => "GameCycle",
+    }
+}
+
+fn result_label(result: &CommandResult) -> &'static str {
+    match result {
+        CommandResult::CrabNotFound => "crab_not_found",
+        CommandResult::NotImplemented => "not_implemented",
+        CommandResult::OutOfEnergy => "out_of_energy",
+        CommandResult::StageNotFound => "stage_not_found",
+        CommandResult::CommandNotAllowed => "command_not_allowed",
+        CommandResult::SandboxNotFound => "sandbox_not_found",
+        CommandResult::Walk(result) if !result.success => "failed",
+        CommandResult::Paint(result) if !result.success => "failed",
+        CommandResult::Pinch(result) if !result.success => "failed",
+        _ => "ok",
+    }
+}
+
+#[derive(Clone)]
+struct MetricsState {
+    metrics: Arc<Metrics>,
+    /// キューに積まれているコマンド数を調べるためのセンダー
+    command_tx: mpsc::Sender<GameCommandCase>,
+}
+
+pub(crate) fn router(metrics: Arc<Metrics>, command_tx: mpsc::Sender<GameCommandCase>) -> Router {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .with_state(MetricsState {
+            metrics,
+            command_tx,
+        })
+}
+
+async fn get_metrics(State(state): State<MetricsState>) -> Result<String, StatusCode> {
+    let metrics = &state.metrics;
+    metrics
+        .queue_depth
+        .set((state.command_tx.max_capacity() - state.command_tx.capacity()) as i64);
+
+    let families = metrics.registry.gather();
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&families, &mut buffer)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    String::from_utf8(buffer).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::player_command::{PlayerCommand, ScanParam, WalkParam, WalkResult};
+    use kani_life_protocol::{Side, Token};
+
+    #[tokio::test]
+    async fn test_observe_command() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let state = GameState::new(4);
+        let token = Token::new();
+        let walk = Command::PlayerCommand(PlayerCommand::Walk(WalkParam {
+            token,
+            side: Side::Right,
+        }));
+        let failed = CommandResult::Walk(WalkResult {
+            success: false,
+            point: 0,
+            total_point: 0,
+            energy: None,
+        });
+        let wait = std::time::Duration::from_millis(10);
+        metrics.observe_command(&walk, &failed, wait, &state);
+        metrics.observe_command(&walk, &failed, wait, &state);
+        let scan = Command::PlayerCommand(PlayerCommand::Scan(ScanParam { token }));
+        metrics.observe_command(&scan, &CommandResult::CrabNotFound, wait, &state);
+
+        let (command_tx, _command_rx) = mpsc::channel(4);
+        let text = get_metrics(State(MetricsState {
+            metrics,
+            command_tx,
+        }))
+        .await
+        .unwrap();
+        assert!(text.contains(r#"kani_life_commands_total{command="Walk",result="failed"} 2"#));
+        assert!(
+            text.contains(r#"kani_life_commands_total{command="Scan",result="crab_not_found"} 1"#)
+        );
+        assert!(text.contains("kani_life_command_queue_wait_seconds_count 3"));
+        assert!(text.contains("kani_life_command_queue_depth 0"));
+        assert!(text.contains("kani_life_crabs 0"));
+    }
+}
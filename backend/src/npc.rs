@@ -0,0 +1,104 @@
+use crate::command::game_cycle_command::GameCycleCommand;
+use crate::command::player_command::SpawnParam;
+use crate::command::{Command, CommandResult};
+use crate::config::NpcConfig;
+use crate::{enqueue_command, GameCommandCase};
+use kani_life::strategy::{GreedyFoodSeeker, Painter, RandomWalker, Strategy, WallFollower};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::{sync::mpsc, time::sleep};
+
+/// NPC の強さ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// コマンドを送るたびに追加で考え込む時間 (ms)
+    fn think_wait(self) -> u64 {
+        match self {
+            Difficulty::Easy => 1500,
+            Difficulty::Normal => 500,
+            Difficulty::Hard => 0,
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty: {}", s)),
+        }
+    }
+}
+
+/// 設定された数の NPC カニを出現させ、それぞれの思考ループスレッドを起動する
+pub(crate) fn spawn_npcs(config: &NpcConfig, command_tx: mpsc::Sender<GameCommandCase>) {
+    for i in 0..config.count {
+        // 戦略は順番に割り当てる
+        let strategy: Box<dyn Strategy> = match i % 4 {
+            0 => Box::<GreedyFoodSeeker>::default(),
+            1 => Box::new(RandomWalker),
+            2 => Box::<Painter>::default(),
+            _ => Box::new(WallFollower),
+        };
+        let param = SpawnParam {
+            name: format!("npc-{:02}-{}", i + 1, strategy.name()),
+            hue: (i as f32 * 67.0) % 360.0,
+            team: None,
+            sandbox: None,
+        };
+        npc_loop(param, strategy, config.difficulty, command_tx.clone());
+    }
+}
+
+/// NPC カニ 1 匹分の思考ループスレッド
+/// プレイヤーと同じく PlayerCommand をキューに積んで操作する
+fn npc_loop(
+    param: SpawnParam,
+    mut strategy: Box<dyn Strategy>,
+    difficulty: Difficulty,
+    command_tx: mpsc::Sender<GameCommandCase>,
+) {
+    tokio::spawn(async move {
+        let name = param.name.clone();
+        let root = tracing::span!(parent: None, tracing::Level::TRACE, "npc_spawn", name);
+        let command = Command::GameCycleCommand(GameCycleCommand::SpawnNpc(param));
+        let Some(response) = enqueue_command(&command_tx, command, root).await else {
+            return;
+        };
+        let CommandResult::Spawn(spawn) = response.result else {
+            tracing::error!("Failed to spawn NPC {}: {:?}", name, response.result);
+            return;
+        };
+        let token = spawn.token;
+        let mut last_result = None;
+        loop {
+            let command =
+                strategy.next_command(token, last_result.as_ref(), &mut rand::thread_rng());
+            let root = tracing::span!(parent: None, tracing::Level::TRACE, "npc_command", name, command = ?command);
+            let command = Command::PlayerCommand(command);
+            let Some(response) = enqueue_command(&command_tx, command, root).await else {
+                break;
+            };
+            if response.result == CommandResult::CrabNotFound {
+                // 同じ名前で Spawn されるなどしてフィールドから除去された
+                tracing::info!("NPC {} has been removed", name);
+                break;
+            }
+            sleep(Duration::from_millis(
+                response.wait + difficulty.think_wait(),
+            ))
+            .await;
+            last_result = Some(response.result);
+        }
+    });
+}
@@ -0,0 +1,95 @@
+//! コマンドの JSON Schema から OpenAPI ドキュメントを組み立てる
+//!
+//! スキーマは kani-life-protocol の型から生成するので、README の型定義のように実装とずれることはない
+
+use axum::response::Html;
+use axum::routing::get;
+use axum::{Json, Router};
+use kani_life::command::player_command::PlayerCommand;
+use kani_life::command::CommandResult;
+use schemars::gen::SchemaSettings;
+use serde_json::json;
+
+pub(crate) fn router() -> Router {
+    Router::new()
+        .route("/api/openapi.json", get(|| async { Json(openapi()) }))
+        .route("/api/docs", get(|| async { Html(DOCS_HTML) }))
+}
+
+pub(crate) fn openapi() -> serde_json::Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let command = gen.subschema_for::<PlayerCommand>();
+    let result = gen.subschema_for::<CommandResult>();
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "kani-life Player Command API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "カニを操作するコマンドの API。`/api/ws` の WebSocket でも、同じ JSON をテキストメッセージで送受信できる。",
+        },
+        "paths": {
+            "/api/command": {
+                "post": {
+                    "summary": "コマンドを送信する",
+                    "description": "コマンドごとの待ち時間が過ぎてから結果が返る。",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": command } },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "コマンドの結果",
+                            "content": { "application/json": { "schema": result } },
+                        },
+                    },
+                },
+            },
+        },
+        "components": { "schemas": gen.definitions() },
+    })
+}
+
+/// Swagger UI 本体は unpkg.com から読み込むので、表示にはブラウザからインターネットにつながる必要がある。
+/// 読み込めないときは `/api/openapi.json` へのリンクだけを出す
+const DOCS_HTML: &str = r##"<!doctype html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>kani-life API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <p id="offline" hidden>
+    Swagger UI を unpkg.com から読み込めませんでした。<a href="/api/openapi.json">OpenAPI ドキュメント (JSON)</a> を直接見てください。
+  </p>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    if (window.SwaggerUIBundle) {
+      window.ui = SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#swagger-ui" });
+    } else {
+      document.getElementById("offline").hidden = false;
+    }
+  </script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_is_up_to_date() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+        let generated = serde_json::to_string_pretty(&openapi()).unwrap() + "\n";
+        if std::env::var("UPDATE_OPENAPI").is_ok() {
+            std::fs::write(path, &generated).unwrap();
+        }
+        let committed = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            generated == committed,
+            "コマンドのスキーマが変わりました。意図した変更なら UPDATE_OPENAPI=1 cargo test で openapi.json を更新してください"
+        );
+    }
+}
@@ -8,6 +8,8 @@ pub(crate) struct Paint {
     #[serde(skip)]
     pub crab_token: Token,
     pub hue: f32,
+    /// ペイントバトルで重ね塗りして守られているマス。相手に塗り替えられない
+    pub protected: bool,
 }
 
 impl Paint {
@@ -16,6 +18,7 @@ impl Paint {
             position,
             crab_token,
             hue,
+            protected: false,
         }
     }
 }
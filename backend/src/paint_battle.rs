@@ -0,0 +1,95 @@
+//! ペイントバトルモード
+//!
+//! 決められた試合時間のあいだ自由に塗り合い、試合が終わったらカニごとに塗ったマスを数える。
+//! 相手が塗ったマスを塗り替えるには多くのポイントがいる。自分のマスを重ね塗りすると守られて、
+//! 相手に塗り替えられなくなる
+
+use crate::crab::Crab;
+use crate::paint::Paint;
+use std::cmp::Reverse;
+
+/// カニ 1 匹分の塗ったマスの数
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrabTally {
+    pub name: String,
+    pub hue: f32,
+    pub cells: usize,
+    /// フィールド全体に対する割合 (%)
+    pub coverage: f64,
+}
+
+/// 塗られたマスの集計。塗ったマスの多い順
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaintTally {
+    pub crabs: Vec<CrabTally>,
+    /// 誰かが塗ったマスの数
+    pub painted: usize,
+    /// フィールド全体に対する塗られたマスの割合 (%)
+    pub coverage: f64,
+}
+
+pub(crate) fn tally(size: u32, crabs: &[Crab], paints: &[Paint]) -> PaintTally {
+    let cells = (size * size).max(1) as f64;
+    let percent = |count: usize| count as f64 * 100.0 / cells;
+    let mut tallies: Vec<CrabTally> = crabs
+        .iter()
+        .map(|crab| {
+            let count = paints.iter().filter(|p| p.crab_token == crab.token).count();
+            CrabTally {
+                name: crab.name.clone(),
+                hue: crab.hue,
+                cells: count,
+                coverage: percent(count),
+            }
+        })
+        .collect();
+    tallies.sort_by_key(|t| Reverse(t.cells));
+    PaintTally {
+        crabs: tallies,
+        painted: paints.len(),
+        coverage: percent(paints.len()),
+    }
+}
+
+/// 試合の進み具合
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PaintBattle {
+    /// 試合が始まった時刻 (ms)
+    #[serde(skip)]
+    started_at: Option<u64>,
+    /// 試合の残り時間 (ms)
+    pub remaining: u64,
+    /// 試合が終わったときの集計
+    pub tally: Option<PaintTally>,
+}
+
+impl PaintBattle {
+    /// 試合を始める (やり直す)
+    pub(crate) fn start(&mut self, now: u64, match_time: u64) {
+        self.started_at = Some(now);
+        self.remaining = match_time;
+        self.tally = None;
+    }
+
+    /// 試合中か
+    pub(crate) fn running(&self) -> bool {
+        self.started_at.is_some() && self.tally.is_none()
+    }
+
+    /// game_cycle ごとの処理。最初の Tick で試合を始め、残り時間を減らす。
+    /// 試合が終わったときだけ true を返すので、呼び出し側で集計を入れる
+    pub(crate) fn tick(&mut self, now: u64, match_time: u64) -> bool {
+        let Some(started_at) = self.started_at else {
+            self.start(now, match_time);
+            return false;
+        };
+        if !self.running() {
+            return false;
+        }
+        self.remaining = (started_at + match_time).saturating_sub(now);
+        self.remaining == 0
+    }
+}
@@ -0,0 +1,25 @@
+//! 霧の中モードでプレイヤーのカニが知覚できること
+//!
+//! フィールド全体が見えると Scan で探す意味がなくなるので、プレイヤーには自分のカニの状態と
+//! Scan の結果だけを配信する
+
+use crate::command::player_command::ScanResult;
+use crate::geometry::{Direction, Position};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Perception {
+    pub name: String,
+    pub point: i32,
+    pub direction: Direction,
+    pub position: Position,
+    /// 残りのエネルギー。エネルギーのルールでないときは None
+    pub energy: Option<u32>,
+    /// 脱皮した回数
+    pub stage: u32,
+    pub team: Option<String>,
+    /// 自分が塗ったまま残っているマス
+    pub paints: Vec<Position>,
+    /// 直前のコマンドが Scan だったときの結果
+    pub scan: Option<ScanResult>,
+}
@@ -0,0 +1,131 @@
+//! カニごとの統計とセッションレポート、チームの順位表、塗られたマスの集計、パズルのステージ一覧、
+//! 練習用のサンドボックスの状態を返す
+//!
+//! 霧の中モードでは、統計・レポート・順位表・塗られたマスの集計は `?key=<KANI_ADMIN_KEY>` を付けたときだけ返す
+
+use crate::admin::SocketAuth;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use kani_life::game_state::GameState;
+use kani_life::paint_battle::PaintTally;
+use kani_life::stage::{self, Stage};
+use kani_life::stats::{self, CrabStats};
+use kani_life::team::TeamScore;
+use kani_life::token::Token;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Clone)]
+struct ReportState {
+    game_state: Arc<Mutex<GameState>>,
+    admin_key: Option<String>,
+    fog_of_war: bool,
+}
+
+impl ReportState {
+    fn authorize(&self, auth: &SocketAuth) -> Result<(), StatusCode> {
+        if auth.can_see_field(&self.admin_key, self.fog_of_war) {
+            Ok(())
+        } else {
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+pub(crate) fn router(
+    game_state: Arc<Mutex<GameState>>,
+    admin_key: Option<String>,
+    fog_of_war: bool,
+) -> Router {
+    Router::new()
+        .route("/api/crabs/:name/stats", get(get_crab_stats))
+        .route("/api/report", get(get_report))
+        .route("/api/teams", get(get_teams))
+        .route("/api/paint-tally", get(get_paint_tally))
+        .route("/api/stages", get(get_stages))
+        .route("/api/sandbox", get(get_sandbox))
+        .with_state(ReportState {
+            game_state,
+            admin_key,
+            fog_of_war,
+        })
+}
+
+async fn get_crab_stats(
+    State(state): State<ReportState>,
+    Path(name): Path<String>,
+    Query(auth): Query<SocketAuth>,
+) -> Result<Json<CrabStats>, StatusCode> {
+    state.authorize(&auth)?;
+    let stats = state.game_state.lock().await.crab_stats(&name);
+    stats.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ReportQuery {
+    /// `markdown` (デフォルト) か `html`
+    format: Option<String>,
+}
+
+async fn get_report(
+    State(state): State<ReportState>,
+    Query(query): Query<ReportQuery>,
+    Query(auth): Query<SocketAuth>,
+) -> Response {
+    if let Err(status) = state.authorize(&auth) {
+        return status.into_response();
+    }
+    let all = state.game_state.lock().await.all_crab_stats();
+    match query.format.as_deref() {
+        Some("html") => Html(stats::html_report(&all)).into_response(),
+        _ => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            stats::markdown_report(&all),
+        )
+            .into_response(),
+    }
+}
+
+/// チームの順位表
+async fn get_teams(
+    State(state): State<ReportState>,
+    Query(auth): Query<SocketAuth>,
+) -> Result<Json<Vec<TeamScore>>, StatusCode> {
+    state.authorize(&auth)?;
+    Ok(Json(state.game_state.lock().await.team_leaderboard()))
+}
+
+/// 塗られたマスの集計。ペイントバトルの試合が終わっていれば、終わったときの集計
+async fn get_paint_tally(
+    State(state): State<ReportState>,
+    Query(auth): Query<SocketAuth>,
+) -> Result<Json<PaintTally>, StatusCode> {
+    state.authorize(&auth)?;
+    Ok(Json(state.game_state.lock().await.paint_tally()))
+}
+
+/// StartStage で遊べるステージの一覧 (やさしい順)
+async fn get_stages() -> Json<Vec<Stage>> {
+    Json(stage::stages())
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SandboxQuery {
+    token: String,
+}
+
+/// サンドボックスの状態。フィールドの `state` イベントと同じ形
+async fn get_sandbox(
+    State(state): State<ReportState>,
+    Query(query): Query<SandboxQuery>,
+) -> Result<Json<GameState>, StatusCode> {
+    let token: Token = query.token.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let game_state = state.game_state.lock().await;
+    let state = game_state
+        .sandbox_state(&token)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(state.clone()))
+}
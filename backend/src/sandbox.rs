@@ -0,0 +1,91 @@
+//! 練習用のサンドボックス
+//!
+//! はじめて書いたプログラムは、バグのあるループでほかの参加者のじゃまをしてしまいがち。
+//! 慣れるまでは自分だけの GameState で練習し、準備ができたら Graduate でみんなのフィールドに出る
+
+use crate::command::game_cycle_command::GameCycleCommand;
+use crate::command::player_command::{PlayerCommand, SandboxMode, SpawnParam};
+use crate::command::{Command, CommandResponse};
+use crate::game_config::GameConfig;
+use crate::game_state::GameState;
+use crate::token::Token;
+
+/// Step モードで毎回待たせる時間 (ms)
+const STEP_WAIT: u64 = 1000;
+
+/// サンドボックスにいるカニ
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Sandbox {
+    pub token: Token,
+    pub name: String,
+    pub hue: f32,
+    /// 卒業したときに入るチーム
+    pub team: Option<String>,
+    mode: SandboxMode,
+    size: u32,
+    config: GameConfig,
+    pub state: GameState,
+}
+
+impl Sandbox {
+    pub(crate) fn new(
+        token: Token,
+        param: &SpawnParam,
+        mode: SandboxMode,
+        size: u32,
+        config: &GameConfig,
+        seed: u64,
+    ) -> Self {
+        Sandbox {
+            token,
+            name: param.name.clone(),
+            hue: param.hue,
+            team: param.team.clone(),
+            mode,
+            size,
+            config: config.clone(),
+            state: GameState::for_sandbox(
+                size,
+                config.clone(),
+                token,
+                &param.name,
+                param.hue,
+                seed,
+            ),
+        }
+    }
+
+    /// カニもごはんも置き直して、はじめからやり直す
+    pub(crate) fn reset(&mut self, seed: u64) {
+        self.state = GameState::for_sandbox(
+            self.size,
+            self.config.clone(),
+            self.token,
+            &self.name,
+            self.hue,
+            seed,
+        );
+    }
+
+    /// サンドボックスの GameState でコマンドを処理する。待ち時間はモードで決まる
+    pub(crate) fn proc_command(&mut self, command: &PlayerCommand, now: u64) -> CommandResponse {
+        self.state.set_now(now);
+        let mut res = self
+            .state
+            .proc_command(&Command::PlayerCommand(command.clone()));
+        // サンドボックスの状態は配信しない
+        res.mutated = false;
+        res.wait = match self.mode {
+            SandboxMode::Fast => 0,
+            SandboxMode::Step => STEP_WAIT,
+        };
+        res
+    }
+
+    /// game_cycle でフィールドにごはんを出すときに、サンドボックスにも出す
+    pub(crate) fn spawn_food(&mut self, now: u64) {
+        self.state.set_now(now);
+        self.state
+            .proc_command(&Command::GameCycleCommand(GameCycleCommand::SpawnFood));
+    }
+}
@@ -0,0 +1,198 @@
+//! サーバーを立てずにゲームを高速に回すシミュレーター
+//!
+//! コマンドの待ち時間は実際には待たず、仮想時計を進めるだけにしている。
+//! シードが同じなら、何度回しても同じ結果になる
+
+use crate::command::game_cycle_command::GameCycleCommand;
+use crate::command::player_command::{PlayerCommand, SpawnParam};
+use crate::command::{Command, CommandResult};
+use crate::game_state::GameState;
+use crate::strategy::Strategy;
+use crate::token::Token;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+struct Bot {
+    name: String,
+    token: Token,
+    strategy: Box<dyn Strategy>,
+    last_result: Option<CommandResult>,
+}
+
+/// 仮想時計で順番に動くもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Actor {
+    GameCycle,
+    Tick,
+    Bot(usize),
+}
+
+pub struct Simulator {
+    state: GameState,
+    bots: Vec<Bot>,
+    /// 戦略に渡す乱数
+    rng: StdRng,
+}
+
+/// 1 ゲーム終了時点のボットの成績
+#[derive(Debug, Clone, PartialEq)]
+pub struct BotScore {
+    pub name: String,
+    pub strategy: &'static str,
+    /// 手元に残っているごはんポイント
+    pub point: i32,
+    /// 塗ったまま残っているマスの数
+    pub paints: usize,
+    /// 同点は同じ順位になる
+    pub rank: usize,
+}
+
+impl BotScore {
+    /// 順位を決めるスコア。塗るのに使ったポイントも失われないように、残りポイントと塗ったマスの合計とする
+    pub fn score(&self) -> i32 {
+        self.point + self.paints as i32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameReport {
+    /// 順位の高い順
+    pub ranking: Vec<BotScore>,
+}
+
+impl Simulator {
+    pub fn new(size: u32, seed: u64) -> Self {
+        Simulator {
+            state: GameState::with_seed(size, seed),
+            bots: vec![],
+            rng: StdRng::seed_from_u64(seed.wrapping_add(1)),
+        }
+    }
+
+    /// ボットを追加し、カニを出現させる
+    pub fn add_bot(&mut self, name: &str, strategy: Box<dyn Strategy>) -> &mut Self {
+        let command = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam {
+            name: name.to_string(),
+            hue: (self.bots.len() as f32 * 67.0) % 360.0,
+            team: None,
+            sandbox: None,
+        }));
+        let CommandResult::Spawn(spawn) = self.state.proc_command(&command).result else {
+            unreachable!("Spawn always succeeds");
+        };
+        self.bots.push(Bot {
+            name: name.to_string(),
+            token: spawn.token,
+            strategy,
+            last_result: None,
+        });
+        self
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// ゲーム内時間で `duration` ミリ秒が経過するまで回す
+    pub fn run(&mut self, duration: u64) -> GameReport {
+        // (時刻, 登録順, 動くもの) の小さい順に取り出す
+        let mut queue = BinaryHeap::new();
+        let mut seq = 0;
+        queue.push(Reverse((0, seq, Actor::GameCycle)));
+        seq += 1;
+        queue.push(Reverse((0, seq, Actor::Tick)));
+        for index in 0..self.bots.len() {
+            seq += 1;
+            queue.push(Reverse((0, seq, Actor::Bot(index))));
+        }
+
+        while let Some(Reverse((now, _, actor))) = queue.pop() {
+            if now > duration {
+                break;
+            }
+            self.state.set_now(now);
+            let response = match actor {
+                Actor::GameCycle => self
+                    .state
+                    .proc_command(&Command::GameCycleCommand(GameCycleCommand::SpawnFood)),
+                Actor::Tick => self
+                    .state
+                    .proc_command(&Command::GameCycleCommand(GameCycleCommand::Tick)),
+                Actor::Bot(index) => {
+                    let bot = &mut self.bots[index];
+                    let command = bot.strategy.next_command(
+                        bot.token,
+                        bot.last_result.as_ref(),
+                        &mut self.rng,
+                    );
+                    let response = self.state.proc_command(&Command::PlayerCommand(command));
+                    bot.last_result = Some(response.result.clone());
+                    response
+                }
+            };
+            seq += 1;
+            // 待ち時間のないコマンドだけを送り続けても時計が進むように、最低 1ms 進める
+            queue.push(Reverse((now + response.wait.max(1), seq, actor)));
+        }
+        self.state.set_now(duration);
+        self.report()
+    }
+
+    fn report(&self) -> GameReport {
+        let mut ranking: Vec<BotScore> = self
+            .bots
+            .iter()
+            .map(|bot| BotScore {
+                name: bot.name.clone(),
+                strategy: bot.strategy.name(),
+                point: self
+                    .state
+                    .crabs()
+                    .iter()
+                    .find(|c| c.get_token() == bot.token)
+                    .map(|c| c.point)
+                    .unwrap_or(0),
+                paints: self
+                    .state
+                    .paints()
+                    .iter()
+                    .filter(|p| p.crab_token == bot.token)
+                    .count(),
+                rank: 0,
+            })
+            .collect();
+        ranking.sort_by_key(|s| Reverse(s.score()));
+        for i in 0..ranking.len() {
+            ranking[i].rank = if i > 0 && ranking[i - 1].score() == ranking[i].score() {
+                ranking[i - 1].rank
+            } else {
+                i + 1
+            };
+        }
+        GameReport { ranking }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy;
+
+    fn simulate(seed: u64) -> GameReport {
+        let mut simulator = Simulator::new(10, seed);
+        for name in ["random", "greedy", "painter", "wall"] {
+            simulator.add_bot(name, strategy::by_name(name).unwrap());
+        }
+        simulator.run(60_000)
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_result() {
+        let report = simulate(42);
+        assert_eq!(report.ranking.len(), 4);
+        assert_eq!(report.ranking[0].rank, 1);
+        assert_eq!(report, simulate(42));
+    }
+}
@@ -0,0 +1,172 @@
+//! 観戦とプレイヤー向けのソケットイベント
+//!
+//! 観戦するソケットはフィールド全体の `state` を受け取る。`follow` か `highlights` を送ると、
+//! 代わりにカメラに映る範囲だけの `view` を受け取るようになり、`unfollow` で元に戻る。
+//! プレイヤーは `subscribe` でトークンを送ると、自分のカニが知覚できることだけを `perception` で受け取る。
+//! ゲームの中で起きたことは、フィールドかカメラを観戦しているソケットに `events` で配信する。
+//! どれを送るかは socket.io のルーム名で覚えておく
+
+use crate::command::CommandResult;
+use crate::game_state::GameState;
+use crate::token::Token;
+use kani_life::camera::Camera;
+use socketioxide::extract::{Data, SocketRef, TryData};
+use socketioxide::SocketIo;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// フィールド全体の `state` を受け取るソケットのルーム
+const FIELD_ROOM: &str = "field";
+/// カメラに映す範囲 (追いかけるカニのまわりのマス数) のデフォルトと上限
+const DEFAULT_RADIUS: u32 = 5;
+const MAX_RADIUS: u32 = 15;
+
+#[derive(Debug, serde::Deserialize)]
+struct FollowParam {
+    name: String,
+    radius: Option<u32>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct HighlightsParam {
+    radius: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SubscribeParam {
+    token: String,
+}
+
+/// `token` のカニの `perception` を受け取るソケットのルーム
+fn crab_room(token: &Token) -> String {
+    format!("crab:{}", token)
+}
+
+fn room(camera: &Camera, radius: Option<u32>) -> String {
+    let radius = radius.unwrap_or(DEFAULT_RADIUS).min(MAX_RADIUS);
+    match camera {
+        Camera::Follow(name) => format!("follow:{}:{}", radius, name),
+        Camera::Highlights => format!("highlights:{}", radius),
+    }
+}
+
+fn parse_room(room: &str) -> Option<(Camera, u32)> {
+    let mut parts = room.splitn(3, ':');
+    let camera = parts.next()?;
+    let radius = parts.next()?.parse().ok()?;
+    match (camera, parts.next()) {
+        ("follow", Some(name)) => Some((Camera::Follow(name.to_string()), radius)),
+        ("highlights", None) => Some((Camera::Highlights, radius)),
+        _ => None,
+    }
+}
+
+/// プレイヤー向けのイベントを登録する
+pub(crate) fn on_connect_player(s: &SocketRef, game_state: Arc<Mutex<GameState>>) {
+    s.on(
+        "subscribe",
+        |s: SocketRef, Data(param): Data<SubscribeParam>| async move {
+            let Ok(token) = param.token.parse::<Token>() else {
+                return;
+            };
+            s.join(crab_room(&token)).ok();
+            if let Some(perception) = game_state.lock().await.perception(&token, None) {
+                s.emit("perception", perception).ok();
+            }
+        },
+    );
+}
+
+/// 観戦のイベントを登録する。登録したソケットはまずフィールド全体を受け取る
+pub(crate) fn on_connect_spectator(s: &SocketRef, game_state: Arc<Mutex<GameState>>) {
+    s.join(FIELD_ROOM).ok();
+    let follow_state = game_state.clone();
+    s.on(
+        "follow",
+        |s: SocketRef, Data(param): Data<FollowParam>| async move {
+            let room = room(&Camera::Follow(param.name), param.radius);
+            watch(&s, room, &follow_state).await;
+        },
+    );
+    s.on(
+        "highlights",
+        // 範囲を指定しなくても (データなしで) 送れる
+        |s: SocketRef, TryData(param): TryData<HighlightsParam>| async move {
+            let room = room(&Camera::Highlights, param.unwrap_or_default().radius);
+            watch(&s, room, &game_state).await;
+        },
+    );
+    s.on("unfollow", |s: SocketRef| {
+        leave_spectator_rooms(&s);
+        s.join(FIELD_ROOM).ok();
+    });
+}
+
+/// 観戦のルームだけを抜ける。プレイヤーとして購読しているルームはそのまま
+fn leave_spectator_rooms(s: &SocketRef) {
+    for room in s.rooms().unwrap_or_default() {
+        if room == FIELD_ROOM || parse_room(&room).is_some() {
+            s.leave(room).ok();
+        }
+    }
+}
+
+/// ルームを移り、今の時点の `view` をすぐに送る
+async fn watch(s: &SocketRef, room: String, game_state: &Mutex<GameState>) {
+    leave_spectator_rooms(s);
+    s.join(room.clone()).ok();
+    let Some((camera, radius)) = parse_room(&room) else {
+        return;
+    };
+    if let Some(view) = game_state.lock().await.view(&camera, radius) {
+        s.emit("view", view).ok();
+    }
+}
+
+/// ゲームの状態が変わったときに、フィールド全体とカメラごとの範囲を配信する
+pub(crate) fn broadcast(io: &SocketIo, state: &GameState) -> bool {
+    let mut ok = io.to(FIELD_ROOM).emit("state", state).is_ok();
+    for room in io.rooms().unwrap_or_default() {
+        let Some((camera, radius)) = parse_room(&room) else {
+            continue;
+        };
+        if let Some(view) = state.view(&camera, radius) {
+            ok &= io.to(room).emit("view", view).is_ok();
+        }
+    }
+    ok
+}
+
+/// コマンドを処理したときに起きたことを、観戦しているソケットに配信する。
+/// カメラのルームには、`view` と同じく映る範囲で起きたことだけを送る
+pub(crate) fn broadcast_events(io: &SocketIo, state: &GameState) -> bool {
+    let mut ok = true;
+    for room in io.rooms().unwrap_or_default() {
+        let events = if room == FIELD_ROOM {
+            state.events().to_vec()
+        } else if let Some((camera, radius)) = parse_room(&room) {
+            state.view_events(&camera, radius)
+        } else {
+            continue;
+        };
+        if !events.is_empty() {
+            ok &= io.to(room).emit("events", events).is_ok();
+        }
+    }
+    ok
+}
+
+/// プレイヤーのコマンドを処理したあとに、そのカニが知覚できることを配信する
+pub(crate) fn perceive(
+    io: &SocketIo,
+    state: &GameState,
+    token: &Token,
+    result: &CommandResult,
+) -> bool {
+    let Some(perception) = state.perception(token, Some(result)) else {
+        return true;
+    };
+    io.to(crab_room(token))
+        .emit("perception", perception)
+        .is_ok()
+}
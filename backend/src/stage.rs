@@ -0,0 +1,209 @@
+//! ひとりで遊ぶパズルのステージ
+//!
+//! プログラミングのハンズオンで、少しずつ難しくなる課題を順に解いてもらうために使う。
+//! ステージはデータで定義し、カニは StartStage でフィールドを離れて専用の GameState で遊ぶ
+
+use crate::command::player_command::{PlayerCommand, StageProgress, StageStatusResult};
+use crate::command::{Command, CommandResponse};
+use crate::game_state::GameState;
+use crate::geometry::{Direction, Position};
+use crate::token::Token;
+
+/// ステージの定義
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stage {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub size: u32,
+    /// カニの最初の位置と向き
+    pub start: Position,
+    pub direction: Direction,
+    /// 最初から持っているごはんポイント
+    pub point: i32,
+    /// サイズ 1 のごはんを置く位置
+    pub foods: Vec<Position>,
+    /// ランダムな位置に置くごはんの数 (遊ぶたびに位置が変わる)
+    pub random_foods: usize,
+    /// 通れない岩。Scan では壁と同じく Wall に見える
+    pub rocks: Vec<Position>,
+    /// 使えるコマンドの種類
+    pub allowed_commands: Vec<&'static str>,
+    pub goal: Goal,
+    /// 使えるステップ (コマンド) の数
+    pub step_budget: u32,
+    /// ステップを半分使ったときのヒントと、失敗したときのヒント
+    #[serde(skip)]
+    pub hints: [&'static str; 2],
+}
+
+/// ステージのゴールの条件
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Goal {
+    /// ごはんをすべて食べる
+    EatAllFoods,
+    /// 指定したマスだけをすべて塗る
+    PaintExactly(Vec<Position>),
+}
+
+impl Goal {
+    pub(crate) fn reached(&self, state: &GameState) -> bool {
+        match self {
+            Goal::EatAllFoods => state.food_count() == 0,
+            Goal::PaintExactly(cells) => {
+                let paints = state.paints();
+                paints.len() == cells.len()
+                    && cells
+                        .iter()
+                        .all(|cell| paints.iter().any(|p| p.position == *cell))
+            }
+        }
+    }
+}
+
+/// 用意されているステージ (やさしい順)
+pub fn stages() -> Vec<Stage> {
+    vec![
+        Stage {
+            id: "behind-the-rock",
+            title: "岩の向こうのごはん",
+            description: "正面の岩をよけて、その向こうにあるごはんを食べよう",
+            size: 5,
+            start: Position::new(2, 4),
+            direction: Direction::E,
+            point: 0,
+            foods: vec![Position::new(2, 1)],
+            random_foods: 0,
+            rocks: vec![Position::new(2, 2)],
+            allowed_commands: vec!["Scan", "Turn", "Walk"],
+            goal: Goal::EatAllFoods,
+            step_budget: 12,
+            hints: [
+                "カニは左右にしか歩けません。Turn で向きを変えると進める方向も変わります",
+                "岩の横の列に移ってから進み、ごはんの横で元の列に戻りましょう",
+            ],
+        },
+        Stage {
+            id: "paint-l",
+            title: "L の字を塗る",
+            description: "(1, 1) から下に 3 マス、そこから右に 2 マスの L の字を塗ろう",
+            size: 5,
+            start: Position::new(1, 1),
+            direction: Direction::E,
+            point: 5,
+            foods: vec![],
+            random_foods: 0,
+            rocks: vec![],
+            allowed_commands: vec!["Turn", "Walk", "Paint"],
+            goal: Goal::PaintExactly(vec![
+                Position::new(1, 1),
+                Position::new(1, 2),
+                Position::new(1, 3),
+                Position::new(2, 3),
+                Position::new(3, 3),
+            ]),
+            step_budget: 15,
+            hints: [
+                "東を向いているとき、右に歩くと南 (下) に進みます",
+                "ポイントは 5 しかないので、塗るのは L の字のマスだけにしましょう",
+            ],
+        },
+        Stage {
+            id: "scan-only",
+            title: "Scan でごはんを探す",
+            description: "どこかにあるごはんを Scan で探して食べよう",
+            size: 7,
+            start: Position::new(3, 3),
+            direction: Direction::N,
+            point: 0,
+            foods: vec![],
+            random_foods: 1,
+            rocks: vec![],
+            allowed_commands: vec!["Scan", "Turn", "Walk"],
+            goal: Goal::EatAllFoods,
+            step_budget: 40,
+            hints: [
+                "Turn しながら Scan して、Food が見える向きを探しましょう",
+                "Food が見えたら、左に Turn して右に Walk、右に Turn すると 1 マス前に進めます",
+            ],
+        },
+    ]
+}
+
+pub fn find(id: &str) -> Option<Stage> {
+    stages().into_iter().find(|s| s.id == id)
+}
+
+/// ステージを遊んでいるカニ
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StagePlay {
+    pub token: Token,
+    pub name: String,
+    pub hue: f32,
+    stage: Stage,
+    state: GameState,
+    steps: u32,
+    progress: StageProgress,
+}
+
+impl StagePlay {
+    pub(crate) fn new(token: Token, name: &str, hue: f32, stage: Stage, seed: u64) -> Self {
+        let state = GameState::for_stage(&stage, token, name, hue, seed);
+        StagePlay {
+            token,
+            name: name.to_string(),
+            hue,
+            stage,
+            state,
+            steps: 0,
+            progress: StageProgress::Playing,
+        }
+    }
+
+    pub(crate) fn status(&self) -> StageStatusResult {
+        let hint = match self.progress {
+            StageProgress::Failed => Some(self.stage.hints[1]),
+            StageProgress::Playing if self.steps * 2 >= self.stage.step_budget => {
+                Some(self.stage.hints[0])
+            }
+            _ => None,
+        };
+        StageStatusResult {
+            stage: self.stage.id.to_string(),
+            progress: self.progress,
+            steps: self.steps,
+            step_budget: self.stage.step_budget,
+            hint: hint.map(str::to_string),
+        }
+    }
+
+    pub(crate) fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// ステージの GameState でコマンドを処理し、ステップを数える。
+    /// ゴールしたか失敗したあとは、コマンドを処理せずに進み具合を返す
+    pub(crate) fn proc_command(&mut self, command: &PlayerCommand, now: u64) -> CommandResponse {
+        if self.progress != StageProgress::Playing {
+            return CommandResponse::stage_status(self.status());
+        }
+        if !self.stage.allowed_commands.contains(&command.name()) {
+            return CommandResponse::command_not_allowed();
+        }
+        self.state.set_now(now);
+        let mut res = self
+            .state
+            .proc_command(&Command::PlayerCommand(command.clone()));
+        // ステージの状態は配信しない
+        res.mutated = false;
+        self.steps += 1;
+        if self.stage.goal.reached(&self.state) {
+            self.progress = StageProgress::Passed;
+        } else if self.steps >= self.stage.step_budget {
+            self.progress = StageProgress::Failed;
+        }
+        res
+    }
+}
@@ -0,0 +1,245 @@
+//! カニごとの行動の統計と、セッション全体のレポート
+//!
+//! ワークショップの後で、参加者に自分のボットがどう動いていたかを見せるために使う
+
+use crate::command::player_command::{FoodKind, PlayerCommand};
+use crate::command::CommandResult;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Walk で移動できなかった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum WalkBlocked {
+    Wall,
+    Crab,
+}
+
+/// カニ 1 匹分の統計。同じ名前で Spawn し直したカニは同じ統計に積み上げる
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrabStats {
+    pub name: String,
+    pub npc: bool,
+    /// Spawn した回数
+    pub spawns: u32,
+    /// コマンドの種類ごとの実行回数
+    pub commands: BTreeMap<String, u32>,
+    pub walk_success: u32,
+    /// 壁にぶつかって移動できなかった回数
+    pub walk_blocked_by_wall: u32,
+    /// ほかのカニにぶつかって移動できなかった回数
+    pub walk_blocked_by_crab: u32,
+    /// Scan の結果ごとの回数
+    pub scans: BTreeMap<String, u32>,
+    /// 食べたごはんのサイズごとの個数
+    pub foods_eaten: BTreeMap<u32, u32>,
+    /// 食べたごはんの種類ごとの個数
+    pub foods_eaten_by_kind: BTreeMap<String, u32>,
+    /// ペイントに使ったポイント
+    pub points_spent_on_paint: i32,
+    /// フィールドにいた時間 (ms)
+    pub time_alive: u64,
+    /// 失敗せずにコマンドを続けられた最長の回数
+    pub longest_streak: u32,
+    /// フィールドに出ている場合は出現した時刻
+    #[serde(skip)]
+    alive_since: Option<u64>,
+    #[serde(skip)]
+    streak: u32,
+}
+
+impl CrabStats {
+    pub(crate) fn new(name: &str, npc: bool) -> Self {
+        CrabStats {
+            name: name.to_string(),
+            npc,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn spawned(&mut self, now: u64) {
+        self.spawns += 1;
+        self.alive_since = Some(now);
+    }
+
+    pub(crate) fn removed(&mut self, now: u64) {
+        if let Some(since) = self.alive_since.take() {
+            self.time_alive += now.saturating_sub(since);
+        }
+    }
+
+    /// 処理したコマンドと結果を記録する
+    pub(crate) fn record(&mut self, command: &PlayerCommand, result: &CommandResult) {
+        *self.commands.entry(command.name().to_string()).or_default() += 1;
+        let success = match result {
+            CommandResult::Walk(result) => {
+                if result.success {
+                    self.walk_success += 1;
+                }
+                result.success
+            }
+            CommandResult::Paint(result) => result.success,
+            CommandResult::Pinch(result) => result.success,
+            CommandResult::CrabNotFound
+            | CommandResult::NotImplemented
+            | CommandResult::OutOfEnergy
+            | CommandResult::StageNotFound
+            | CommandResult::CommandNotAllowed
+            | CommandResult::SandboxNotFound => false,
+            CommandResult::Scan(result) => {
+                *self
+                    .scans
+                    .entry(format!("{:?}", result.what_you_can_see))
+                    .or_default() += 1;
+                true
+            }
+            _ => true,
+        };
+        if success {
+            self.streak += 1;
+            self.longest_streak = self.longest_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+
+    /// 塗るのに使ったポイント。ペイントバトルでは塗ったマスと塗り替えたマスの分だけかかる
+    pub(crate) fn painted(&mut self, cost: i32) {
+        self.points_spent_on_paint += cost;
+    }
+
+    pub(crate) fn ate(&mut self, size: u32, kind: FoodKind) {
+        *self.foods_eaten.entry(size).or_default() += 1;
+        *self
+            .foods_eaten_by_kind
+            .entry(format!("{:?}", kind))
+            .or_default() += 1;
+    }
+
+    pub(crate) fn walk_blocked(&mut self, by: WalkBlocked) {
+        match by {
+            WalkBlocked::Wall => self.walk_blocked_by_wall += 1,
+            WalkBlocked::Crab => self.walk_blocked_by_crab += 1,
+        }
+    }
+
+    /// `now` 時点の統計。フィールドにいるカニは今までの時間を time_alive に含める
+    pub(crate) fn snapshot(&self, now: u64) -> CrabStats {
+        let mut stats = self.clone();
+        stats.removed(now);
+        stats
+    }
+
+    fn total_commands(&self) -> u32 {
+        self.commands.values().sum()
+    }
+}
+
+const SUMMARY_HEADER: [&str; 9] = [
+    "カニ",
+    "コマンド数",
+    "Walk 成功",
+    "壁に衝突",
+    "カニに衝突",
+    "食べたごはん",
+    "ペイント",
+    "生存時間 (秒)",
+    "最長連続成功",
+];
+
+fn summary_row(stats: &CrabStats) -> [String; 9] {
+    let name = if stats.npc {
+        format!("{} (NPC)", stats.name)
+    } else {
+        stats.name.clone()
+    };
+    [
+        name,
+        stats.total_commands().to_string(),
+        stats.walk_success.to_string(),
+        stats.walk_blocked_by_wall.to_string(),
+        stats.walk_blocked_by_crab.to_string(),
+        stats.foods_eaten.values().sum::<u32>().to_string(),
+        stats.points_spent_on_paint.to_string(),
+        format!("{:.1}", stats.time_alive as f64 / 1000.0),
+        stats.longest_streak.to_string(),
+    ]
+}
+
+/// `種類: 回数` を並べた 1 行にする
+fn breakdown<K: std::fmt::Display>(counts: &BTreeMap<K, u32>) -> String {
+    if counts.is_empty() {
+        return "-".to_string();
+    }
+    counts
+        .iter()
+        .map(|(k, v)| format!("{}: {}", k, v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn details(stats: &CrabStats) -> [(&'static str, String); 4] {
+    [
+        ("コマンド", breakdown(&stats.commands)),
+        ("Scan の結果", breakdown(&stats.scans)),
+        ("食べたごはん (サイズ: 個数)", breakdown(&stats.foods_eaten)),
+        (
+            "食べたごはん (種類: 個数)",
+            breakdown(&stats.foods_eaten_by_kind),
+        ),
+    ]
+}
+
+/// セッション全体のレポートを Markdown で作る
+pub fn markdown_report(stats: &[CrabStats]) -> String {
+    let mut md = String::from("# kani-life セッションレポート\n\n");
+    let _ = writeln!(md, "| {} |", SUMMARY_HEADER.join(" | "));
+    let _ = writeln!(md, "|{}", " --- |".repeat(SUMMARY_HEADER.len()));
+    for s in stats {
+        let row = summary_row(s).map(|cell| cell.replace('|', "\\|"));
+        let _ = writeln!(md, "| {} |", row.join(" | "));
+    }
+    for s in stats {
+        let _ = write!(md, "\n## {}\n\n", s.name);
+        for (label, value) in details(s) {
+            let _ = writeln!(md, "- {}: {}", label, value);
+        }
+    }
+    md
+}
+
+/// セッション全体のレポートを HTML で作る
+pub fn html_report(stats: &[CrabStats]) -> String {
+    let mut html = String::from(
+        "<!doctype html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>kani-life セッションレポート</title>\n</head>\n<body>\n<h1>kani-life セッションレポート</h1>\n<table border=\"1\">\n<tr>",
+    );
+    for header in SUMMARY_HEADER {
+        let _ = write!(html, "<th>{}</th>", header);
+    }
+    html.push_str("</tr>\n");
+    for s in stats {
+        html.push_str("<tr>");
+        for cell in summary_row(s) {
+            let _ = write!(html, "<td>{}</td>", escape_html(&cell));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    for s in stats {
+        let _ = writeln!(html, "<h2>{}</h2>\n<ul>", escape_html(&s.name));
+        for (label, value) in details(s) {
+            let _ = writeln!(html, "<li>{}: {}</li>", label, escape_html(&value));
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// カニ名は参加者が自由に決めるのでエスケープする
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,264 @@
+use crate::command::player_command::{
+    PaintParam, PlayerCommand, ScanParam, TurnParam, WalkParam, WhatYouCanSee,
+};
+use crate::command::CommandResult;
+use crate::geometry::Side;
+use crate::token::Token;
+use rand::{Rng, RngCore};
+use std::collections::VecDeque;
+
+/// カニの行動を決める戦略
+/// 直前のコマンド結果だけを手がかりに、次に送る PlayerCommand を決める
+///
+/// サーバーの NPC とシミュレーターの両方で使われる。
+/// 乱数は渡された `rng` から取ると、シミュレーターでシードを固定したときに結果が再現できる
+pub trait Strategy: Send {
+    fn name(&self) -> &'static str;
+
+    fn next_command(
+        &mut self,
+        token: Token,
+        last_result: Option<&CommandResult>,
+        rng: &mut dyn RngCore,
+    ) -> PlayerCommand;
+}
+
+/// 名前から組み込みの戦略を作る
+pub fn by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "random" => Some(Box::new(RandomWalker)),
+        "greedy" => Some(Box::<GreedyFoodSeeker>::default()),
+        "painter" => Some(Box::<Painter>::default()),
+        "wall" => Some(Box::new(WallFollower)),
+        _ => None,
+    }
+}
+
+fn random_side(rng: &mut dyn RngCore) -> Side {
+    if rng.gen_bool(0.5) {
+        Side::Right
+    } else {
+        Side::Left
+    }
+}
+
+/// ランダムに歩き回る
+pub struct RandomWalker;
+
+impl Strategy for RandomWalker {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn next_command(
+        &mut self,
+        token: Token,
+        _: Option<&CommandResult>,
+        rng: &mut dyn RngCore,
+    ) -> PlayerCommand {
+        let side = random_side(rng);
+        // たまに向きを変える
+        if rng.gen_ratio(1, 5) {
+            PlayerCommand::Turn(TurnParam { token, side })
+        } else {
+            PlayerCommand::Walk(WalkParam { token, side })
+        }
+    }
+}
+
+/// Scan でごはんを探し、見つけたらまっすぐ向かう
+#[derive(Default)]
+pub struct GreedyFoodSeeker {
+    /// 実行待ちのコマンド
+    plan: VecDeque<PlayerCommand>,
+    /// ごはんが見つからないまま旋回した回数
+    turns: u8,
+}
+
+impl Strategy for GreedyFoodSeeker {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn next_command(
+        &mut self,
+        token: Token,
+        last_result: Option<&CommandResult>,
+        rng: &mut dyn RngCore,
+    ) -> PlayerCommand {
+        if let Some(command) = self.plan.pop_front() {
+            return command;
+        }
+        match last_result {
+            Some(CommandResult::Scan(result)) if result.what_you_can_see == WhatYouCanSee::Food => {
+                // 左を向いてから右に歩くと、元の正面方向に 1 マス進める
+                // 進んだら元の向きに戻って、またスキャンする
+                self.turns = 0;
+                self.plan.push_back(PlayerCommand::Walk(WalkParam {
+                    token,
+                    side: Side::Right,
+                }));
+                self.plan.push_back(PlayerCommand::Turn(TurnParam {
+                    token,
+                    side: Side::Right,
+                }));
+                PlayerCommand::Turn(TurnParam {
+                    token,
+                    side: Side::Left,
+                })
+            }
+            Some(CommandResult::Scan(_)) if self.turns < 3 => {
+                self.turns += 1;
+                PlayerCommand::Turn(TurnParam {
+                    token,
+                    side: Side::Right,
+                })
+            }
+            Some(CommandResult::Scan(_)) => {
+                // 四方にごはんがないので場所を変える
+                self.turns = 0;
+                PlayerCommand::Walk(WalkParam {
+                    token,
+                    side: random_side(rng),
+                })
+            }
+            _ => PlayerCommand::Scan(ScanParam { token }),
+        }
+    }
+}
+
+/// ごはんを探し、ポイントがあれば歩いた先を塗る
+#[derive(Default)]
+pub struct Painter {
+    seeker: GreedyFoodSeeker,
+}
+
+impl Strategy for Painter {
+    fn name(&self) -> &'static str {
+        "painter"
+    }
+
+    fn next_command(
+        &mut self,
+        token: Token,
+        last_result: Option<&CommandResult>,
+        rng: &mut dyn RngCore,
+    ) -> PlayerCommand {
+        match last_result {
+            Some(CommandResult::Walk(result)) if result.success && result.total_point > 0 => {
+                PlayerCommand::Paint(PaintParam { token })
+            }
+            _ => self.seeker.next_command(token, last_result, rng),
+        }
+    }
+}
+
+/// 壁やカニにぶつかるまで右に歩き、ぶつかったら左を向く
+pub struct WallFollower;
+
+impl Strategy for WallFollower {
+    fn name(&self) -> &'static str {
+        "wall"
+    }
+
+    fn next_command(
+        &mut self,
+        token: Token,
+        last_result: Option<&CommandResult>,
+        _: &mut dyn RngCore,
+    ) -> PlayerCommand {
+        match last_result {
+            Some(CommandResult::Walk(result)) if !result.success => {
+                PlayerCommand::Turn(TurnParam {
+                    token,
+                    side: Side::Left,
+                })
+            }
+            _ => PlayerCommand::Walk(WalkParam {
+                token,
+                side: Side::Right,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::player_command::{ScanResult, WalkResult};
+
+    #[test]
+    fn test_greedy_food_seeker_approaches_food() {
+        let token = Token::new();
+        let mut rng = rand::thread_rng();
+        let mut strategy = GreedyFoodSeeker::default();
+        assert!(matches!(
+            strategy.next_command(token, None, &mut rng),
+            PlayerCommand::Scan(_)
+        ));
+        // ごはんが見えたら 左旋回 -> 右に歩く -> 右旋回 で 1 マス前進する
+        let found = CommandResult::Scan(ScanResult {
+            what_you_can_see: WhatYouCanSee::Food,
+            food_kind: None,
+            team: None,
+        });
+        assert!(matches!(
+            strategy.next_command(token, Some(&found), &mut rng),
+            PlayerCommand::Turn(TurnParam {
+                side: Side::Left,
+                ..
+            })
+        ));
+        assert!(matches!(
+            strategy.next_command(token, Some(&CommandResult::Turn), &mut rng),
+            PlayerCommand::Walk(WalkParam {
+                side: Side::Right,
+                ..
+            })
+        ));
+        let walked = CommandResult::Walk(WalkResult {
+            success: true,
+            point: 0,
+            total_point: 0,
+            energy: None,
+        });
+        assert!(matches!(
+            strategy.next_command(token, Some(&walked), &mut rng),
+            PlayerCommand::Turn(TurnParam {
+                side: Side::Right,
+                ..
+            })
+        ));
+        assert!(matches!(
+            strategy.next_command(token, Some(&CommandResult::Turn), &mut rng),
+            PlayerCommand::Scan(_)
+        ));
+    }
+
+    #[test]
+    fn test_wall_follower_turns_when_blocked() {
+        let token = Token::new();
+        let mut rng = rand::thread_rng();
+        let mut strategy = WallFollower;
+        let blocked = CommandResult::Walk(WalkResult {
+            success: false,
+            point: 0,
+            total_point: 0,
+            energy: None,
+        });
+        assert!(matches!(
+            strategy.next_command(token, Some(&blocked), &mut rng),
+            PlayerCommand::Turn(TurnParam {
+                side: Side::Left,
+                ..
+            })
+        ));
+        assert!(matches!(
+            strategy.next_command(token, Some(&CommandResult::Turn), &mut rng),
+            PlayerCommand::Walk(WalkParam {
+                side: Side::Right,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+//! チーム戦の順位表
+//!
+//! ワークショップでペアを組んだ参加者のカニを同じチームにして、チームごとに競えるようにする
+
+use std::cmp::Reverse;
+
+/// チーム 1 つ分の成績
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamScore {
+    pub team: String,
+    /// チームの色相。最初にチームに入ったカニの色相
+    pub hue: f32,
+    /// フィールドにいるメンバーのカニ名
+    pub members: Vec<String>,
+    /// チームのごはんポイント。合計するルールではメンバーの合計、共有するルールでは共有しているポイント
+    pub point: i32,
+    /// チームで塗ったまま残っているマスの数
+    pub paints: usize,
+    /// 同点は同じ順位になる
+    pub rank: usize,
+}
+
+impl TeamScore {
+    /// 順位を決めるスコア。シミュレーターのボットと同じく、残りポイントと塗ったマスの合計とする
+    pub fn score(&self) -> i32 {
+        self.point + self.paints as i32
+    }
+}
+
+/// スコアの高い順に並べて順位をつける
+pub(crate) fn rank(mut scores: Vec<TeamScore>) -> Vec<TeamScore> {
+    scores.sort_by_key(|s| Reverse(s.score()));
+    for i in 0..scores.len() {
+        scores[i].rank = if i > 0 && scores[i - 1].score() == scores[i].score() {
+            scores[i - 1].rank
+        } else {
+            i + 1
+        };
+    }
+    scores
+}
@@ -1,39 +1,151 @@
+use crate::config::TelemetryConfig;
 use opentelemetry::trace::{TraceError, TracerProvider as _};
 use opentelemetry::KeyValue;
-use opentelemetry_sdk::trace::RandomIdGenerator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::trace::{RandomIdGenerator, Sampler};
 use opentelemetry_sdk::{runtime, Resource};
-use opentelemetry_sdk::{trace as sdktrace, trace::TracerProvider};
 use opentelemetry_semantic_conventions::resource::SERVICE_NAME;
+use std::str::FromStr;
 use tracing::Subscriber;
+use tracing_subscriber::filter::{EnvFilter, Targets};
 use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::Registry;
-
-fn init_tracer_provider() -> Result<TracerProvider, TraceError> {
-    let exporter = opentelemetry_otlp::SpanExporter::builder()
-        .with_tonic()
-        .build()?;
-
-    Ok(TracerProvider::builder()
-        .with_batch_exporter(exporter, runtime::Tokio)
-        .with_config(
-            sdktrace::Config::default()
-                .with_id_generator(RandomIdGenerator::default())
-                .with_resource(Resource::new(vec![KeyValue::new(
-                    SERVICE_NAME,
-                    "kani-life",
-                )])),
-        )
-        .build())
-}
-
-fn init_tracer(name: &str) -> sdktrace::Tracer {
-    let provider = init_tracer_provider().expect("Failed to initialize tracer provider.");
-    provider.tracer(name.to_owned())
-}
-
-pub fn init_tracing_subscriber(name: &str) -> impl Subscriber + Send + Sync + 'static {
-    let tracer = init_tracer(name);
-    let telemetry = tracing_opentelemetry::layer().with_tracer(tracer);
-    let subscriber = Registry::default().with(telemetry);
-    subscriber
+use tracing_subscriber::{Layer, Registry};
+
+/// トレースの送信先
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TraceExporter {
+    /// トレースを送らない
+    None,
+    /// 標準出力に書き出す
+    Stdout,
+    OtlpGrpc,
+    OtlpHttp,
+}
+
+impl FromStr for TraceExporter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(TraceExporter::None),
+            "stdout" => Ok(TraceExporter::Stdout),
+            "otlp-grpc" => Ok(TraceExporter::OtlpGrpc),
+            "otlp-http" => Ok(TraceExporter::OtlpHttp),
+            _ => Err(format!("unknown trace exporter: {}", s)),
+        }
+    }
+}
+
+/// ログの出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format: {}", s)),
+        }
+    }
+}
+
+fn init_tracer_provider(config: &TelemetryConfig) -> Result<Option<TracerProvider>, TraceError> {
+    let builder = TracerProvider::builder();
+    let builder = match config.exporter {
+        TraceExporter::None => return Ok(None),
+        TraceExporter::Stdout => builder.with_batch_exporter(
+            opentelemetry_stdout::SpanExporter::default(),
+            runtime::Tokio,
+        ),
+        TraceExporter::OtlpGrpc => builder.with_batch_exporter(
+            opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .build()?,
+            runtime::Tokio,
+        ),
+        TraceExporter::OtlpHttp => builder.with_batch_exporter(
+            opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .build()?,
+            runtime::Tokio,
+        ),
+    };
+
+    Ok(Some(
+        builder
+            .with_sampler(sampler(config.sample_ratio))
+            .with_id_generator(RandomIdGenerator::default())
+            .with_resource(Resource::new(vec![KeyValue::new(
+                SERVICE_NAME,
+                "kani-life",
+            )]))
+            .build(),
+    ))
+}
+
+/// 親のスパンがあればその判断に従い、なければ `sample_ratio` の割合で記録する
+fn sampler(sample_ratio: f64) -> Sampler {
+    let root = if sample_ratio >= 1.0 {
+        Sampler::AlwaysOn
+    } else if sample_ratio > 0.0 {
+        Sampler::TraceIdRatioBased(sample_ratio)
+    } else {
+        Sampler::AlwaysOff
+    };
+    Sampler::ParentBased(Box::new(root))
+}
+
+pub fn init_tracing_subscriber(
+    name: &str,
+    config: &TelemetryConfig,
+) -> Result<impl Subscriber + Send + Sync + 'static, TraceError> {
+    // トレースは kani-life 自身のスパンだけを送る
+    let telemetry = init_tracer_provider(config)?.map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(name.to_owned()))
+            .with_filter(Targets::new().with_target("kani_life", tracing::Level::TRACE))
+    });
+    let log = match config.log_format {
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    Ok(Registry::default()
+        .with(telemetry)
+        .with(log.with_filter(filter)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exporter_and_format() {
+        assert_eq!("none".parse(), Ok(TraceExporter::None));
+        assert_eq!("Stdout".parse(), Ok(TraceExporter::Stdout));
+        assert_eq!("otlp-grpc".parse(), Ok(TraceExporter::OtlpGrpc));
+        assert_eq!("OTLP-HTTP".parse(), Ok(TraceExporter::OtlpHttp));
+        assert!("jaeger".parse::<TraceExporter>().is_err());
+        assert_eq!("pretty".parse(), Ok(LogFormat::Pretty));
+        assert_eq!("JSON".parse(), Ok(LogFormat::Json));
+        assert!("text".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_sampler() {
+        let root = |ratio| match sampler(ratio) {
+            Sampler::ParentBased(root) => format!("{:?}", root),
+            sampler => panic!("not parent based: {:?}", sampler),
+        };
+        assert_eq!(root(1.0), "AlwaysOn");
+        assert_eq!(root(0.25), "TraceIdRatioBased(0.25)");
+        assert_eq!(root(0.0), "AlwaysOff");
+        assert_eq!(root(-1.0), "AlwaysOff");
+    }
 }
@@ -0,0 +1,70 @@
+//! WaitFor で、関係のあることが起きるまで待つ
+//!
+//! GameState はその場で待てないので、待ち始めたときの様子を `Watch` に残しておき、サーバーの command_processor が
+//! コマンドを処理するたびに `check` で確かめる。ほかにコマンドが来なくてもタイムアウトするように、
+//! command_processor は `deadline` に Wake を送って自分を起こす。Scan をくり返してごはんが出るのを待つボットのためのもの
+
+use crate::command::player_command::{ScanResult, WaitForParam, WaitForResult, WaitReason};
+use crate::command::CommandResponse;
+use crate::event::GameEvent;
+use crate::game_state::GameState;
+use crate::token::Token;
+
+/// timeout を省略したときに待つ時間 (ms)
+const DEFAULT_TIMEOUT: u64 = 10 * 1000;
+/// これより長くは待たない (ms)
+const MAX_TIMEOUT: u64 = 60 * 1000;
+
+/// WaitFor で待ち始めたときの様子
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watch {
+    token: Token,
+    scan: ScanResult,
+    point: i32,
+    /// この時刻 (ms) を過ぎたらタイムアウトする
+    deadline: u64,
+}
+
+impl Watch {
+    pub(crate) fn new(param: &WaitForParam, scan: ScanResult, point: i32, now: u64) -> Self {
+        let timeout = param.timeout.unwrap_or(DEFAULT_TIMEOUT).min(MAX_TIMEOUT);
+        Watch {
+            token: param.token,
+            scan,
+            point,
+            deadline: now + timeout,
+        }
+    }
+
+    /// タイムアウトする時刻 (ms)
+    pub fn deadline(&self) -> u64 {
+        self.deadline
+    }
+
+    /// 直前のコマンドを処理した後の `state` を見て、待つのをやめるなら WaitFor の結果を返す。
+    /// 待っている間にカニがいなくなったら CrabNotFound
+    pub fn check(&self, state: &GameState, now: u64) -> Option<CommandResponse> {
+        let Some(result) = state.wait_for_result(&self.token, WaitReason::Timeout) else {
+            return Some(CommandResponse::crab_not_found());
+        };
+        let food_spawned = state
+            .events()
+            .iter()
+            .any(|e| matches!(e, GameEvent::FoodSpawned { .. }));
+        let reason = if food_spawned {
+            WaitReason::FoodSpawned
+        } else if result.scan != self.scan {
+            WaitReason::ScanChanged
+        } else if result.total_point != self.point {
+            WaitReason::PointChanged
+        } else if now >= self.deadline {
+            WaitReason::Timeout
+        } else {
+            return None;
+        };
+        Some(CommandResponse::wait_for(WaitForResult {
+            reason,
+            ..result
+        }))
+    }
+}
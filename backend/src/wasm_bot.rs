@@ -0,0 +1,596 @@
+//! アップロードされた WebAssembly モジュールでカニを動かすランタイム
+//!
+//! モジュールは引数・戻り値なしの `tick` 関数をエクスポートする。
+//! サーバーは `tick` を繰り返し呼び出し、モジュールは `kani` モジュールからインポートした
+//! ホスト関数でカニを操作する。ホスト関数は通常の PlayerCommand としてキューに積まれる。
+//!
+//! - `scan() -> i32` 正面に見えるもの (0: Food, 1: Crab, 2: Wall, 3: Flag, 4: Base, 5: Hill)
+//! - `turn(side: i32)` 0 なら右、それ以外なら左に旋回
+//! - `walk(side: i32) -> i32` 0 なら右、それ以外なら左に移動。成功すると 1
+//! - `paint() -> i32` 今いるマスを塗る。成功すると 1
+//! - `pinch() -> i32` 正面のカニをはさむ。成功すると 1
+//! - `log(ptr: i32, len: i32)` メモリ上の UTF-8 文字列をログに残す
+//!
+//! ボットはそれぞれ専用のスレッドで動かし、同時に動かせる数には上限がある。
+//! `DELETE /api/bots/{id}` で止めるか、同じ名前で新しいボットをアップロードすると止まる。
+//! カニがいなくなっていることには、次にコマンドを送ったときに気づいて止まる
+
+use crate::command::player_command::{
+    PaintParam, PinchParam, PlayerCommand, ScanParam, SpawnParam, TurnParam, WalkParam,
+    WhatYouCanSee,
+};
+use crate::command::{Command, CommandResponse, CommandResult};
+use crate::geometry::Side;
+use crate::token::Token;
+use crate::GameCommandCase;
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use wasmi::{
+    Caller, Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+/// 1 回の tick で使える燃料 (おおよそ実行できる命令数)
+const FUEL_PER_TICK: u64 = 10_000_000;
+/// 1 回の tick にかけられる時間 (コマンドの待ち時間を含む)
+const TICK_TIME_LIMIT: Duration = Duration::from_secs(5);
+/// tick と tick の間の最低間隔
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+/// モジュールが使えるメモリの上限
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+/// 保持するログの行数
+const LOG_CAPACITY: usize = 100;
+/// 同時に動かせるボットの数
+const MAX_RUNNING_BOTS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "type")]
+pub(crate) enum BotStatus {
+    Running,
+    /// トラップなどで停止した
+    Stopped {
+        reason: String,
+    },
+}
+
+/// アップロードしたユーザーに返す実行状況
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BotReport {
+    status: BotStatus,
+    ticks: u64,
+    logs: VecDeque<String>,
+}
+
+impl BotReport {
+    fn log(&mut self, line: String) {
+        if self.logs.len() >= LOG_CAPACITY {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(line);
+    }
+}
+
+type SharedReport = Arc<Mutex<BotReport>>;
+
+/// アップロードされたボット 1 体分
+#[derive(Debug)]
+struct BotHandle {
+    name: String,
+    report: SharedReport,
+    /// 立てると次の tick の前にボットが止まる
+    stop: Arc<AtomicBool>,
+}
+
+impl BotHandle {
+    fn running(&self) -> bool {
+        !self.stop.load(Ordering::Relaxed)
+            && self.report.lock().unwrap().status == BotStatus::Running
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BotState {
+    command_tx: mpsc::Sender<GameCommandCase>,
+    engine: Engine,
+    bots: Arc<Mutex<HashMap<Token, BotHandle>>>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct UploadParam {
+    name: String,
+    hue: f32,
+    team: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadResult {
+    /// ログを取得するための ID
+    id: Token,
+    /// 動かしているカニのトークン
+    token: Token,
+}
+
+pub(crate) fn router(command_tx: mpsc::Sender<GameCommandCase>) -> Router {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let state = BotState {
+        command_tx,
+        engine: Engine::new(&config),
+        bots: Default::default(),
+    };
+    Router::new()
+        .route("/api/bots", post(upload_bot))
+        .route("/api/bots/:id", get(get_bot_report))
+        .route("/api/bots/:id", delete(delete_bot))
+        .with_state(state)
+}
+
+/// WebAssembly モジュールを受け取り、カニを出現させて動かし始める
+async fn upload_bot(
+    State(state): State<BotState>,
+    Query(param): Query<UploadParam>,
+    wasm: Bytes,
+) -> Result<Json<UploadResult>, (StatusCode, String)> {
+    let module = compile(&state.engine, &wasm).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let running = state
+        .bots
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|b| b.running())
+        .count();
+    if running >= MAX_RUNNING_BOTS {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "too many bots are running".to_string(),
+        ));
+    }
+    let name = param.name.clone();
+
+    let command = Command::PlayerCommand(PlayerCommand::Spawn(SpawnParam {
+        name: param.name,
+        hue: param.hue,
+        team: param.team,
+        sandbox: None,
+    }));
+    let root = tracing::span!(parent: None, tracing::Level::TRACE, "upload_bot");
+    let response = crate::enqueue_command(&state.command_tx, command, root).await;
+    let Some(CommandResponse {
+        result: CommandResult::Spawn(spawn),
+        ..
+    }) = response
+    else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to spawn crab".to_string(),
+        ));
+    };
+
+    let id = Token::new();
+    let report = Arc::new(Mutex::new(BotReport {
+        status: BotStatus::Running,
+        ticks: 0,
+        logs: VecDeque::new(),
+    }));
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let mut bots = state.bots.lock().unwrap();
+        // 同じ名前のカニは Spawn し直されたので、前のボットは止める
+        for bot in bots.values().filter(|b| b.name == name) {
+            bot.stop.store(true, Ordering::Relaxed);
+        }
+        let handle = BotHandle {
+            name,
+            report: report.clone(),
+            stop: stop.clone(),
+        };
+        bots.insert(id, handle);
+    }
+
+    let host = HostState {
+        token: spawn.token,
+        command_tx: state.command_tx.clone(),
+        deadline: Instant::now(),
+        report,
+        limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+    };
+    let engine = state.engine.clone();
+    // tokio のブロッキングスレッドを使い続けないように、専用のスレッドで動かす
+    let spawned = std::thread::Builder::new()
+        .name("wasm-bot".to_string())
+        .spawn(move || run_bot(engine, module, host, stop));
+    if let Err(e) = spawned {
+        tracing::error!("Failed to start bot: {}", e);
+        state.bots.lock().unwrap().remove(&id);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to start bot".to_string(),
+        ));
+    }
+
+    Ok(Json(UploadResult {
+        id,
+        token: spawn.token,
+    }))
+}
+
+async fn get_bot_report(
+    State(state): State<BotState>,
+    Path(id): Path<Token>,
+) -> Result<Json<BotReport>, StatusCode> {
+    let bots = state.bots.lock().unwrap();
+    let bot = bots.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let report = bot.report.lock().unwrap().clone();
+    Ok(Json(report))
+}
+
+/// ボットを止めて取り除く。カニはフィールドに残る
+async fn delete_bot(State(state): State<BotState>, Path(id): Path<Token>) -> StatusCode {
+    match state.bots.lock().unwrap().remove(&id) {
+        Some(bot) => {
+            bot.stop.store(true, Ordering::Relaxed);
+            StatusCode::NO_CONTENT
+        }
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+/// モジュールをコンパイルし、`tick` がエクスポートされているか確かめる
+fn compile(engine: &Engine, wasm: &[u8]) -> Result<Module, String> {
+    let module = Module::new(engine, wasm).map_err(|e| format!("invalid module: {}", e))?;
+    let has_tick = module
+        .exports()
+        .any(|export| export.name() == "tick" && export.ty().func().is_some());
+    if !has_tick {
+        return Err("module must export a `tick` function".to_string());
+    }
+    Ok(module)
+}
+
+/// ホスト関数から参照するボットごとの状態
+struct HostState {
+    token: Token,
+    command_tx: mpsc::Sender<GameCommandCase>,
+    /// 実行中の tick の締め切り
+    deadline: Instant,
+    report: SharedReport,
+    limits: StoreLimits,
+}
+
+impl HostState {
+    /// コマンドをキューに積み、結果を待つ (ブロッキング)
+    fn send(&self, command: PlayerCommand) -> Result<CommandResult, wasmi::Error> {
+        if Instant::now() > self.deadline {
+            return Err(wasmi::Error::new("tick time limit exceeded"));
+        }
+        let root =
+            tracing::span!(parent: None, tracing::Level::TRACE, "wasm_bot", command = ?command);
+        let (response_tx, response_rx) = oneshot::channel::<CommandResponse>();
+        self.command_tx
+            .blocking_send(GameCommandCase {
+                command: Command::PlayerCommand(command),
+                callback_tx: response_tx,
+                parent_span: root,
+                enqueued_at: Instant::now(),
+            })
+            .map_err(|_| wasmi::Error::new("server is shutting down"))?;
+        let response = response_rx
+            .blocking_recv()
+            .map_err(|_| wasmi::Error::new("server is shutting down"))?;
+        if response.result == CommandResult::CrabNotFound {
+            return Err(wasmi::Error::new("crab not found"));
+        }
+        if response.wait > 0 {
+            std::thread::sleep(Duration::from_millis(response.wait));
+        }
+        Ok(response.result)
+    }
+}
+
+fn side(value: i32) -> Side {
+    if value == 0 {
+        Side::Right
+    } else {
+        Side::Left
+    }
+}
+
+fn linker(engine: &Engine) -> Result<Linker<HostState>, wasmi::Error> {
+    let mut linker = Linker::<HostState>::new(engine);
+    linker.func_wrap(
+        "kani",
+        "scan",
+        |caller: Caller<'_, HostState>| -> Result<i32, wasmi::Error> {
+            let host = caller.data();
+            match host.send(PlayerCommand::Scan(ScanParam { token: host.token }))? {
+                CommandResult::Scan(result) => Ok(match result.what_you_can_see {
+                    WhatYouCanSee::Food => 0,
+                    WhatYouCanSee::Crab => 1,
+                    WhatYouCanSee::Wall => 2,
+                    WhatYouCanSee::Flag => 3,
+                    WhatYouCanSee::Base => 4,
+                    WhatYouCanSee::Hill => 5,
+                }),
+                _ => Ok(-1),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "kani",
+        "turn",
+        |caller: Caller<'_, HostState>, value: i32| -> Result<(), wasmi::Error> {
+            let host = caller.data();
+            host.send(PlayerCommand::Turn(TurnParam {
+                token: host.token,
+                side: side(value),
+            }))?;
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "kani",
+        "walk",
+        |caller: Caller<'_, HostState>, value: i32| -> Result<i32, wasmi::Error> {
+            let host = caller.data();
+            match host.send(PlayerCommand::Walk(WalkParam {
+                token: host.token,
+                side: side(value),
+            }))? {
+                CommandResult::Walk(result) => Ok(result.success as i32),
+                _ => Ok(0),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "kani",
+        "paint",
+        |caller: Caller<'_, HostState>| -> Result<i32, wasmi::Error> {
+            let host = caller.data();
+            match host.send(PlayerCommand::Paint(PaintParam { token: host.token }))? {
+                CommandResult::Paint(result) => Ok(result.success as i32),
+                _ => Ok(0),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "kani",
+        "pinch",
+        |caller: Caller<'_, HostState>| -> Result<i32, wasmi::Error> {
+            let host = caller.data();
+            match host.send(PlayerCommand::Pinch(PinchParam { token: host.token }))? {
+                CommandResult::Pinch(result) => Ok(result.success as i32),
+                _ => Ok(0),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "kani",
+        "log",
+        |caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            let memory = caller
+                .get_export("memory")
+                .and_then(|export| export.into_memory())
+                .ok_or_else(|| wasmi::Error::new("module must export `memory` to log"))?;
+            let mut buffer = vec![0; len.clamp(0, 1024) as usize];
+            memory
+                .read(&caller, ptr as usize, &mut buffer)
+                .map_err(|e| wasmi::Error::new(e.to_string()))?;
+            let line = String::from_utf8_lossy(&buffer).into_owned();
+            caller.data().report.lock().unwrap().log(line);
+            Ok(())
+        },
+    )?;
+    Ok(linker)
+}
+
+/// モジュールをインスタンス化し、`tick` を取り出す
+fn instantiate(
+    engine: &Engine,
+    module: &Module,
+    host: HostState,
+) -> Result<(Store<HostState>, TypedFunc<(), ()>), wasmi::Error> {
+    let mut store = Store::new(engine, host);
+    store.limiter(|host| &mut host.limits);
+    let instance = linker(engine)?
+        .instantiate(&mut store, module)?
+        .start(&mut store)?;
+    let tick = instance.get_typed_func::<(), ()>(&store, "tick")?;
+    Ok((store, tick))
+}
+
+/// `tick` を 1 回呼ぶ。ホスト関数を呼ばずに計算し続けた場合も、終わった後に時間を確かめる
+fn run_tick(
+    store: &mut Store<HostState>,
+    tick: &TypedFunc<(), ()>,
+    time_limit: Duration,
+) -> Result<(), String> {
+    let started = Instant::now();
+    store.data_mut().deadline = started + time_limit;
+    store.set_fuel(FUEL_PER_TICK).map_err(|e| e.to_string())?;
+    tick.call(&mut *store, ()).map_err(|e| e.to_string())?;
+    if started.elapsed() > time_limit {
+        return Err("tick time limit exceeded".to_string());
+    }
+    Ok(())
+}
+
+/// ボット 1 体分の実行ループ (専用のスレッドで動かす)
+fn run_bot(engine: Engine, module: Module, host: HostState, stop_flag: Arc<AtomicBool>) {
+    let report = host.report.clone();
+    let stop = |reason: String| {
+        let mut report = report.lock().unwrap();
+        report.log(format!("stopped: {}", reason));
+        report.status = BotStatus::Stopped { reason };
+    };
+
+    let (mut store, tick) = match instantiate(&engine, &module, host) {
+        Ok(instance) => instance,
+        Err(e) => return stop(e.to_string()),
+    };
+
+    loop {
+        if stop_flag.load(Ordering::Relaxed) {
+            return stop("removed".to_string());
+        }
+        let started = Instant::now();
+        if let Err(e) = run_tick(&mut store, &tick, TICK_TIME_LIMIT) {
+            return stop(e);
+        }
+        report.lock().unwrap().ticks += 1;
+        // コマンドを送らない tick が CPU を使い切らないように間隔をあける
+        if let Some(rest) = TICK_INTERVAL.checked_sub(started.elapsed()) {
+            std::thread::sleep(rest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::player_command::{PaintResult, ScanResult, WalkResult};
+
+    fn engine() -> Engine {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        Engine::new(&config)
+    }
+
+    /// `wat` のモジュールで tick を呼べる状態にする。コマンドは `command_tx` に届く
+    fn load(
+        wat: &str,
+        command_tx: mpsc::Sender<GameCommandCase>,
+    ) -> (Store<HostState>, TypedFunc<(), ()>) {
+        let engine = engine();
+        let module = compile(&engine, &wat::parse_str(wat).unwrap()).unwrap();
+        let host = HostState {
+            token: Token::new(),
+            command_tx,
+            deadline: Instant::now(),
+            report: Arc::new(Mutex::new(BotReport {
+                status: BotStatus::Running,
+                ticks: 0,
+                logs: VecDeque::new(),
+            })),
+            limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+        };
+        instantiate(&engine, &module, host).unwrap()
+    }
+
+    /// コマンドの種類名を記録し、決まった結果を返すだけのゲームプロセッサ
+    fn stub_processor(mut command_rx: mpsc::Receiver<GameCommandCase>) -> Vec<&'static str> {
+        let mut names = vec![];
+        while let Some(case) = command_rx.blocking_recv() {
+            let Command::PlayerCommand(command) = &case.command else {
+                continue;
+            };
+            names.push(command.name());
+            let result = match command {
+                PlayerCommand::Walk(_) => CommandResult::Walk(WalkResult {
+                    success: true,
+                    point: 0,
+                    total_point: 1,
+                    energy: None,
+                }),
+                PlayerCommand::Paint(_) => CommandResult::Paint(PaintResult {
+                    success: true,
+                    your_paints: vec![],
+                    total_point: 0,
+                    energy: None,
+                }),
+                PlayerCommand::Scan(_) => CommandResult::Scan(ScanResult {
+                    what_you_can_see: WhatYouCanSee::Wall,
+                    food_kind: None,
+                    team: None,
+                }),
+                _ => CommandResult::Turn,
+            };
+            let response = CommandResponse {
+                result,
+                wait: 0,
+                mutated: false,
+            };
+            case.callback_tx.send(response).ok();
+        }
+        names
+    }
+
+    #[test]
+    fn test_host_calls() {
+        let (command_tx, command_rx) = mpsc::channel(1);
+        let processor = std::thread::spawn(move || stub_processor(command_rx));
+        // 歩けたら塗り、正面が壁 (2) なら左に回る
+        let (mut store, tick) = load(
+            r#"(module
+                (import "kani" "walk" (func $walk (param i32) (result i32)))
+                (import "kani" "paint" (func $paint (result i32)))
+                (import "kani" "scan" (func $scan (result i32)))
+                (import "kani" "turn" (func $turn (param i32)))
+                (func (export "tick")
+                    (if (call $walk (i32.const 0))
+                        (then (drop (call $paint))))
+                    (if (i32.eq (call $scan) (i32.const 2))
+                        (then (call $turn (i32.const 1))))))"#,
+            command_tx,
+        );
+        run_tick(&mut store, &tick, TICK_TIME_LIMIT).unwrap();
+        drop(store);
+        assert_eq!(
+            processor.join().unwrap(),
+            vec!["Walk", "Paint", "Scan", "Turn"]
+        );
+    }
+
+    #[test]
+    fn test_fuel_and_time_limits() {
+        let (command_tx, _command_rx) = mpsc::channel(1);
+        // 燃料を使い切るまで止まらない
+        let (mut store, tick) = load(
+            r#"(module (func (export "tick") (loop (br 0))))"#,
+            command_tx.clone(),
+        );
+        let error = run_tick(&mut store, &tick, TICK_TIME_LIMIT).unwrap_err();
+        assert!(error.contains("fuel"), "{}", error);
+
+        // ホスト関数を呼ぶときに締め切りを過ぎていれば止める
+        let (mut store, tick) = load(
+            r#"(module
+                (import "kani" "scan" (func $scan (result i32)))
+                (func (export "tick") (drop (call $scan))))"#,
+            command_tx.clone(),
+        );
+        let error = run_tick(&mut store, &tick, Duration::ZERO).unwrap_err();
+        assert!(error.contains("tick time limit exceeded"), "{}", error);
+
+        // ホスト関数を呼ばずに計算し続けても、終わった後に時間を確かめる
+        let (mut store, tick) = load(
+            r#"(module (func (export "tick") (local $i i32)
+                (loop (br_if 0 (i32.lt_u
+                    (local.tee $i (i32.add (local.get $i) (i32.const 1)))
+                    (i32.const 100000))))))"#,
+            command_tx,
+        );
+        let error = run_tick(&mut store, &tick, Duration::ZERO).unwrap_err();
+        assert_eq!(error, "tick time limit exceeded");
+    }
+
+    #[test]
+    fn test_module_without_tick_is_rejected() {
+        let engine = engine();
+        // 何もエクスポートしない空のモジュール
+        let wasm = b"\0asm\x01\0\0\0";
+        assert_eq!(
+            compile(&engine, wasm).unwrap_err(),
+            "module must export a `tick` function"
+        );
+        assert!(compile(&engine, b"not wasm").is_err());
+    }
+}
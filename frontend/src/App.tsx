@@ -2,6 +2,8 @@ import './App.css'
 import GameField from "./components/game-field.tsx";
 import {Provider} from "jotai";
 import Bgm from "./components/bgm.tsx";
+import PaintBattleBoard from "./components/paint-battle-board.tsx";
+import CameraLabel from "./components/camera-label.tsx";
 
 function App() {
 
@@ -9,6 +11,8 @@ function App() {
         <>
             <Provider>
                 <GameField/>
+                <PaintBattleBoard/>
+                <CameraLabel/>
                 <Bgm/>
             </Provider>
         </>
@@ -0,0 +1,25 @@
+import styled from "styled-components";
+import {useAtomValue} from "jotai";
+import {followingAtom} from "../feature/atoms.ts";
+
+/**
+ * 観戦のカメラで追いかけているカニ名
+ */
+function CameraLabel() {
+    const following = useAtomValue(followingAtom)
+    if (following == null) {
+        return null
+    }
+    return <Label>🎥 {following}</Label>
+}
+
+const Label = styled.div`
+    position: fixed;
+    top: 0;
+    left: 0;
+    padding: 8px 12px;
+    font-size: 2vh;
+    background-color: rgba(255, 255, 255, 0.8);
+`;
+
+export default CameraLabel
@@ -2,12 +2,15 @@ import styled from "styled-components";
 import {range} from "../feature/helper.ts";
 import {useAtomValue} from "jotai";
 import {
+    captureTheFlagAtom,
     type Crab,
     crabsAtom,
     decoratedCellsAtom,
     foodsAtom,
+    type FoodKind,
     foodSizeMaxAtom,
     gameFieldSizeAtom,
+    kingOfTheHillAtom,
     paintedCellsAtom
 } from "../feature/atoms.ts";
 import {useDecoration} from "../feature/use-decoration.ts";
@@ -93,10 +96,21 @@ function GridCell({lineNo, cellNo}: GridCellProps) {
     const key = `${cellNo},${lineNo}`
     const color = paintedCells[key] ?? decoratedCells[key]
     const food = useAtomValue(foodsAtom).find((f) => f.position.y == lineNo && f.position.x == cellNo)
+    const ctf = useAtomValue(captureTheFlagAtom)
+    const base = ctf?.bases.find((b) => Math.abs(b.center.x - cellNo) <= 1 && Math.abs(b.center.y - lineNo) <= 1)
+    const flag = ctf?.flags.find((f) => f.carriedBy == null && f.position.y == lineNo && f.position.x == cellNo)
+    const hill = useAtomValue(kingOfTheHillAtom)?.hills.find((h) => Math.abs(h.center.x - cellNo) <= 1 && Math.abs(h.center.y - lineNo) <= 1)
+    const outline = base
+        ? `dashed 1px ${teamColor(base.team)}`
+        : hill && `solid 2px ${hill.contested ? '#E53935' : hill.holder != null ? teamColor(hill.holder) : '#F5B800'}`
     return (
-        <GridCellInner $gameFieldSize={gameFieldSize} style={{backgroundColor: color}}>
+        <GridCellInner $gameFieldSize={gameFieldSize}
+                       style={{backgroundColor: color, outline: outline || undefined}}>
             {food && (
-                <Food size={food.size}/>
+                <Food size={food.size} kind={food.kind}/>
+            )}
+            {flag && (
+                <FlagMark style={{color: teamColor(flag.team)}}>⚑</FlagMark>
             )}
         </GridCellInner>
     )
@@ -113,19 +127,46 @@ const GridCellInner = styled.div<{ $paintColor?: string; $gameFieldSize: number;
     background-color: ${props => props.$paintColor || "transparent"}
 `;
 
+/**
+ * チーム名から陣地や旗、丘の色を決める
+ */
+function teamColor(team: string) {
+    const hue = [...team].reduce((acc, c) => (acc * 31 + c.charCodeAt(0)) % 360, 0)
+    return `hsl(${hue}deg 80% 40%)`
+}
+
+const FlagMark = styled.div`
+    display: flex;
+    align-items: center;
+    justify-content: center;
+    width: 100%;
+    height: 100%;
+    font-size: 3vh;
+`;
+
 type FoodProps = {
     size: number
+    kind?: FoodKind
 }
 
-function Food({size}: FoodProps) {
+// ごはんの種類ごとの色 [外側, ハイライト]
+const foodColors: Record<FoodKind, [string, string]> = {
+    Normal: ["#FD8075", "#FFAAAA"],
+    Golden: ["#F5B800", "#FFE27A"],
+    Poison: ["#8E44AD", "#C39BD3"],
+    Rotting: ["#8D7B4A", "#BFAF80"],
+}
+
+function Food({size, kind}: FoodProps) {
+    const [fill, highlight] = kind ? foodColors[kind] : foodColors.Normal
     const foodSizeMax = useAtomValue(foodSizeMaxAtom)
     // r を 5 から 15 の範囲にする
     const r = ((size - 1) / (foodSizeMax - 1)) * 6 + 6
     return (
         <FoodWrap>
             <svg viewBox="0 0 36 36">
-                <circle cx="18" cy="18" r={r} fill="#FD8075"/>
-                <circle cx="15" cy="15" r={r - 5} fill="#FFAAAA"/>
+                <circle cx="18" cy="18" r={r} fill={fill}/>
+                <circle cx="15" cy="15" r={r - 5} fill={highlight}/>
             </svg>
         </FoodWrap>
     )
@@ -145,13 +186,14 @@ function Crab({info}: CrabProps) {
     const darker = `hsl(${info.hue}deg 95% 32%)`
     const baseColor = `hsl(${info.hue}deg 77% 42%)`
     const highlight = `hsl(${info.hue}deg 72% 52%)`
+    const carrying = useAtomValue(captureTheFlagAtom)?.flags.some((f) => f.carriedBy == info.name)
     return (
         <CrabBase $gameFieldSize={gameFieldSize}
                   style={crabPosition(info.position.x, info.position.y, gameFieldSize)}>
             <svg
                 xmlns="http://www.w3.org/2000/svg"
                 viewBox="0 0 36 36"
-                style={{transform: `rotate(${direction2rotate(info.direction)}deg)`}}
+                style={{transform: `rotate(${direction2rotate(info.direction)}deg) scale(${stage2scale(info.stage)})`}}
             >
                 <path fill={darker}
                       d="M6.96 20.637c.068.639-.543 1.228-1.368 1.315-.824.089-1.547-.357-1.615-.995-.068-.639.544-1.227 1.368-1.314.824-.089 1.547.356 1.615.994zm2.087 2.717c.125.818-1.756 2.544-2.576 2.669-.819.125-1.584-.438-1.708-1.257-.125-.818.58-1.14 1.398-1.265.819-.124 2.761-.965 2.886-.147zm1.783 2.104c.173.81-1.628 3.927-2.438 4.1-.811.173-1.645.146-1.817-.665-.173-.81.306-1.688 1.116-1.861.81-.174 2.966-2.384 3.139-1.574zm3.853.858c.165.811-1.338 4.354-2.15 4.519-.812.165-1.439.451-1.604-.36-.165-.812.261-1.975 1.006-2.58.644-.523 2.584-2.39 2.748-1.579z"/>
@@ -187,7 +229,7 @@ function Crab({info}: CrabProps) {
                 <path fill={highlight}
                       d="M10.793 24.433c0-.414 1.782 2.25 7.207 2.25s7.208-2.664 7.208-2.25c0 .414-2.244 3.75-7.208 3.75s-7.207-3.336-7.207-3.75z"/>
             </svg>
-            <CrabInfo style={{color: darker}}>{info.name} {info.point}pt</CrabInfo>
+            <CrabInfo style={{color: darker}}>{info.npc ? '🤖' : ''}{info.team != null ? `[${info.team}] ` : ''}{carrying ? '⚑' : ''}{info.name} {info.point}pt{info.energy != null ? ` ⚡${info.energy}` : ''}</CrabInfo>
         </CrabBase>
     )
 }
@@ -203,6 +245,11 @@ function crabPosition(x: number, y: number, gameFieldSize: number): { left: stri
     }
 }
 
+// 脱皮して成長したカニほど大きく描く
+function stage2scale(stage: number) {
+    return 1 + stage * 0.2;
+}
+
 function direction2rotate(direction: Crab["direction"]) {
     switch (direction) {
         case "N":
@@ -0,0 +1,41 @@
+import styled from "styled-components";
+import {useAtomValue} from "jotai";
+import {paintBattleAtom} from "../feature/atoms.ts";
+
+/**
+ * ペイントバトルの残り時間と、試合が終わったときの集計
+ */
+function PaintBattleBoard() {
+    const battle = useAtomValue(paintBattleAtom)
+    if (battle == null) {
+        return null
+    }
+    const seconds = Math.ceil(battle.remaining / 1000)
+    return (
+        <Board>
+            {battle.tally == null ? (
+                <div>⏱ {Math.floor(seconds / 60)}:{String(seconds % 60).padStart(2, '0')}</div>
+            ) : (
+                <>
+                    <div>🏁 {battle.tally.coverage.toFixed(1)}%</div>
+                    {battle.tally.crabs.map((crab) => (
+                        <div key={crab.name} style={{color: `hsl(${crab.hue}deg 95% 32%)`}}>
+                            {crab.name} {crab.cells} ({crab.coverage.toFixed(1)}%)
+                        </div>
+                    ))}
+                </>
+            )}
+        </Board>
+    )
+}
+
+const Board = styled.div`
+    position: fixed;
+    top: 0;
+    right: 0;
+    padding: 8px 12px;
+    font-size: 2vh;
+    background-color: rgba(255, 255, 255, 0.8);
+`;
+
+export default PaintBattleBoard
@@ -4,7 +4,7 @@ import {atom} from "jotai";
 export const gameFieldSizeAtom = atom(30);
 export const foodSizeMaxAtom = atom(3);
 
-type Position = {
+export type Position = {
     x: number
     y: number
 }
@@ -12,6 +12,8 @@ type Position = {
 export type Paint = {
     position: Position
     hue: number
+    // ペイントバトルで重ね塗りして守られているマス
+    protected: boolean
 }
 
 // export type DecoratedCell = {
@@ -26,13 +28,75 @@ export type PaintedCellDict = Record<string, string>
 export const paintedCellsAtom = atom<PaintedCellDict>({});
 
 
+export type FoodKind = "Normal" | "Golden" | "Poison" | "Rotting"
+
 export type Food = {
     id: string
     position: Position
     size: number
+    // ごはんの種類を明かすルールのときだけ送られる
+    kind?: FoodKind
 }
 export const foodsAtom = atom<Food[]>([]);
 
+// キャプチャー・ザ・フラッグの陣地。中心のまわり 3x3 マス
+export type Base = {
+    team: string
+    center: Position
+    captures: number
+}
+
+export type Flag = {
+    team: string
+    position: Position
+    // 旗を持っているカニの名前
+    carriedBy: string | null
+}
+
+export type CaptureTheFlag = {
+    bases: Base[]
+    flags: Flag[]
+}
+export const captureTheFlagAtom = atom<CaptureTheFlag | null>(null);
+
+// キング・オブ・ザ・ヒルの丘。中心のまわり 3x3 マス
+export type Hill = {
+    center: Position
+    // 丘を取っているチーム名 (チームに入っていなければカニ名)
+    holder: string | null
+    contested: boolean
+}
+
+export type KingOfTheHill = {
+    hills: Hill[]
+}
+export const kingOfTheHillAtom = atom<KingOfTheHill | null>(null);
+
+export type CrabTally = {
+    name: string
+    hue: number
+    cells: number
+    // フィールド全体に対する割合 (%)
+    coverage: number
+}
+
+export type PaintTally = {
+    crabs: CrabTally[]
+    painted: number
+    coverage: number
+}
+
+export type PaintBattle = {
+    // 試合の残り時間 (ms)
+    remaining: number
+    // 試合が終わったときの集計
+    tally: PaintTally | null
+}
+export const paintBattleAtom = atom<PaintBattle | null>(null);
+
+// 観戦のカメラで追いかけているカニ名。フィールド全体を見ているときは null
+export const followingAtom = atom<string | null>(null);
+
 
 export type Crab = {
     name: string
@@ -40,6 +104,13 @@ export type Crab = {
     point: number
     direction: "N" | "E" | "S" | "W"
     position: Position
+    npc: boolean
+    // エネルギーのルールでないときは null
+    energy: number | null
+    // 脱皮した回数
+    stage: number
+    // 所属するチーム名。チームに入っていないときは null
+    team: string | null
 }
 export const crabsAtom = atom<Crab[]>([
     // {name: "test001", hue: 370, point: 0, direction: "N", position: {x: 10, y: 10}},
@@ -1,3 +1,6 @@
 import {io} from 'socket.io-client';
 
-export const socket = io('/');
+// 霧の中モードでは `?key=<KANI_ADMIN_KEY>` を付けて開いたときだけフィールド全体が届く
+const key = new URLSearchParams(window.location.search).get('key')
+
+export const socket = io('/', key != null ? {auth: {key}} : {});
@@ -1,5 +1,21 @@
 import {useSetAtom} from 'jotai';
-import {Crab, crabsAtom, Food, foodsAtom, gameFieldSizeAtom, Paint, paintedCellsAtom} from "./atoms.ts";
+import {
+    CaptureTheFlag,
+    captureTheFlagAtom,
+    Crab,
+    crabsAtom,
+    Food,
+    followingAtom,
+    foodsAtom,
+    gameFieldSizeAtom,
+    KingOfTheHill,
+    kingOfTheHillAtom,
+    Paint,
+    PaintBattle,
+    paintBattleAtom,
+    paintedCellsAtom,
+    Position
+} from "./atoms.ts";
 import {useEffect, useState} from "react";
 import {socket} from "./socket.ts";
 
@@ -8,6 +24,58 @@ type State = {
     crabs: Crab[]
     foods: Food[]
     paints: Paint[]
+    // キャプチャー・ザ・フラッグモードのときだけ
+    captureTheFlag?: CaptureTheFlag
+    // キング・オブ・ザ・ヒルモードのときだけ
+    kingOfTheHill?: KingOfTheHill
+    // ペイントバトルモードのときだけ
+    paintBattle?: PaintBattle
+}
+
+// 観戦のカメラに映る範囲だけの状態。位置はフィールドの座標のまま
+type View = State & {
+    following: string
+    viewport: {
+        left: number
+        top: number
+        size: number
+    }
+}
+
+/**
+ * `?follow=<カニ名>` でそのカニを、`?highlights` で直前にポイントを取ったカニを追いかける。
+ * `&radius=<マス数>` で映す範囲を変えられる
+ */
+const requestCamera = () => {
+    const params = new URLSearchParams(window.location.search)
+    const radius = params.has('radius') ? Number(params.get('radius')) : undefined
+    const follow = params.get('follow')
+    if (follow != null) {
+        socket.emit('follow', {name: follow, radius})
+    } else if (params.has('highlights')) {
+        socket.emit('highlights', {radius})
+    } else {
+        socket.emit('get')
+    }
+}
+
+// カメラに映る範囲の左上が (0, 0) になるようにずらす
+const toViewportState = (view: View): State => {
+    const shift = (p: Position): Position => ({x: p.x - view.viewport.left, y: p.y - view.viewport.top})
+    return {
+        size: view.viewport.size,
+        crabs: view.crabs.map(c => ({...c, position: shift(c.position)})),
+        foods: view.foods.map(f => ({...f, position: shift(f.position)})),
+        paints: view.paints.map(p => ({...p, position: shift(p.position)})),
+        captureTheFlag: view.captureTheFlag && {
+            bases: view.captureTheFlag.bases.map(b => ({...b, center: shift(b.center)})),
+            flags: view.captureTheFlag.flags.map(f => ({...f, position: shift(f.position)})),
+        },
+        kingOfTheHill: view.kingOfTheHill && {
+            hills: view.kingOfTheHill.hills.map(h => ({...h, center: shift(h.center)})),
+        },
+        paintBattle: view.paintBattle,
+    }
 }
 
 export type WebSocket = {
@@ -20,11 +88,15 @@ export const useWebSocket = (): WebSocket => {
     const setFoods = useSetAtom(foodsAtom);
     const setCrabs = useSetAtom(crabsAtom);
     const setPaintedCells = useSetAtom(paintedCellsAtom);
+    const setCaptureTheFlag = useSetAtom(captureTheFlagAtom);
+    const setKingOfTheHill = useSetAtom(kingOfTheHillAtom);
+    const setPaintBattle = useSetAtom(paintBattleAtom);
+    const setFollowing = useSetAtom(followingAtom);
 
     useEffect(() => {
         function onConnect() {
             setIsConnected(true);
-            socket.emit('get');
+            requestCamera();
             console.log('socket connected')
         }
 
@@ -38,16 +110,26 @@ export const useWebSocket = (): WebSocket => {
             setGameFieldSize(state.size)
             setFoods(state.foods)
             setCrabs(state.crabs)
+            setCaptureTheFlag(state.captureTheFlag ?? null)
+            setKingOfTheHill(state.kingOfTheHill ?? null)
+            setPaintBattle(state.paintBattle ?? null)
             const paintedCells: Record<string, string> = state.paints.reduce((acc: Record<string, string>, p) => {
-                acc[`${p.position.x},${p.position.y}`] = `hsla(${p.hue}, 70%, 30%, 0.5)`
+                // 守られているマスは濃く塗る
+                acc[`${p.position.x},${p.position.y}`] = `hsla(${p.hue}, 70%, 30%, ${p.protected ? 0.8 : 0.5})`
                 return acc
             }, {})
             setPaintedCells(paintedCells)
         }
 
+        function onNewView(view: View) {
+            setFollowing(view.following)
+            onNewState(toViewportState(view))
+        }
+
         socket.on('connect', onConnect)
         socket.on('disconnect', onDisconnect)
         socket.on('state', onNewState)
+        socket.on('view', onNewView)
 
         socket.connect()
         console.log('socket connect has called')
@@ -57,6 +139,7 @@ export const useWebSocket = (): WebSocket => {
             socket.off('connect', onConnect)
             socket.off('disconnect', onDisconnect)
             socket.off('state', onNewState)
+            socket.off('view', onNewView)
         }
     }, []);
 